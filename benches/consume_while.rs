@@ -0,0 +1,24 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use unidown::Cursor;
+
+fn digit_run(c: &mut Criterion) {
+    let input = "7".repeat(64 * 1024) + "x";
+
+    c.bench_function("consume_while digits", |b| {
+        b.iter(|| {
+            let mut cursor = Cursor::from(black_box(input.as_str()));
+            cursor.consume_while(|ch| ch.is_ascii_digit());
+            cursor.position()
+        })
+    });
+    c.bench_function("consume_while_ascii digits", |b| {
+        b.iter(|| {
+            let mut cursor = Cursor::from(black_box(input.as_str()));
+            cursor.consume_while_ascii(|byte| byte.is_ascii_digit());
+            cursor.position()
+        })
+    });
+}
+
+criterion_group!(benches, digit_run);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use unidown::unescape::{unescape_cow, unescape_to_string, Mode};
+
+fn no_escapes(c: &mut Criterion) {
+    let literal = "plain text without any escapes in it ".repeat(256);
+
+    c.bench_function("unescape_to_string no escapes", |b| {
+        b.iter(|| unescape_to_string(black_box(&literal), Mode::Double).map(|s| s.len()))
+    });
+    c.bench_function("unescape_cow no escapes", |b| {
+        b.iter(|| unescape_cow(black_box(&literal), Mode::Double).map(|s| s.len()))
+    });
+}
+
+criterion_group!(benches, no_escapes);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+//! Char classes shared by the cursor's lexing helpers and unescaping.
+
+pub fn is_hex_digit(ch: char) -> bool {
+    ch.is_ascii_hexdigit()
+}
+
+/// Value of a hex digit of either case.
+pub fn hex_value(ch: char) -> Option<u8> {
+    ch.to_digit(16).map(|digit| digit as u8)
+}
+
+/// Whether `ch` can start an identifier, by XID rules with the
+/// `unicode-ident` feature and ASCII otherwise. `_` always can.
+#[cfg(feature = "unicode-ident")]
+pub fn is_ident_start(ch: char) -> bool {
+    ch == '_' || unicode_ident::is_xid_start(ch)
+}
+
+#[cfg(not(feature = "unicode-ident"))]
+pub fn is_ident_start(ch: char) -> bool {
+    ch == '_' || ch.is_ascii_alphabetic()
+}
+
+/// Whether `ch` can continue an identifier, see `is_ident_start`.
+#[cfg(feature = "unicode-ident")]
+pub fn is_ident_continue(ch: char) -> bool {
+    unicode_ident::is_xid_continue(ch)
+}
+
+#[cfg(not(feature = "unicode-ident"))]
+pub fn is_ident_continue(ch: char) -> bool {
+    ch == '_' || ch.is_ascii_alphanumeric()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert!(is_hex_digit('0') && is_hex_digit('f') && is_hex_digit('F'));
+        assert!(!is_hex_digit('g') && !is_hex_digit('G') && !is_hex_digit('ф'));
+        assert_eq!(hex_value('9'), Some(9));
+        assert_eq!(hex_value('a'), Some(10));
+        assert_eq!(hex_value('F'), Some(15));
+        assert_eq!(hex_value('g'), None);
+    }
+
+    #[test]
+    fn test_ident() {
+        assert!(is_ident_start('_') && is_ident_start('a') && is_ident_start('Z'));
+        assert!(!is_ident_start('1') && !is_ident_start('-'));
+        assert!(is_ident_continue('1') && is_ident_continue('_'));
+        assert!(!is_ident_continue('-'));
+    }
+}
@@ -0,0 +1,63 @@
+/// How a cursor counts columns and splits lines. The default keeps tabs one
+/// column wide and ends lines at `\n`, with an optional `\r` before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorConfig {
+    /// Columns between tab stops in `line_col` and `measure_indent`. 0 is
+    /// treated as 1.
+    pub tab_width: usize,
+    pub newline: NewlineStyle,
+    /// Whether `measure_indent` rejects indentation mixing tabs and spaces.
+    pub strict_indent: bool,
+}
+
+impl CursorConfig {
+    /// Width in columns of `text` at the start of a line, with tabs
+    /// advancing to the next tab stop.
+    pub(crate) fn columns(self, text: &str) -> usize {
+        let tab_width = self.tab_width.max(1);
+        text.chars().fold(0, |col, ch| match ch {
+            '\t' => col + tab_width - col % tab_width,
+            _ => col + 1,
+        })
+    }
+}
+
+impl Default for CursorConfig {
+    fn default() -> Self {
+        Self {
+            tab_width: 1,
+            newline: NewlineStyle::default(),
+            strict_indent: false,
+        }
+    }
+}
+
+/// Which line endings end a line, for line counting and line iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NewlineStyle {
+    /// `\n`, and a `\r` right before it is part of the line ending.
+    #[default]
+    Auto,
+    /// Only `\n`, a `\r` before it is part of the line.
+    Lf,
+    /// Only `\r\n`, a lone `\n` is part of the line.
+    CrLf,
+}
+
+impl NewlineStyle {
+    pub(crate) fn terminator(self) -> &'static str {
+        match self {
+            NewlineStyle::Auto | NewlineStyle::Lf => "\n",
+            NewlineStyle::CrLf => "\r\n",
+        }
+    }
+
+    /// Strips the line ending off a line that includes it.
+    pub(crate) fn strip(self, line: &str) -> &str {
+        let line = line.strip_suffix(self.terminator()).unwrap_or(line);
+        match self {
+            NewlineStyle::Auto => line.strip_suffix('\r').unwrap_or(line),
+            NewlineStyle::Lf | NewlineStyle::CrLf => line,
+        }
+    }
+}
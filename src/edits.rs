@@ -0,0 +1,159 @@
+//! Replacements keyed by byte ranges of an input, applied in one pass.
+
+use std::ops::Range;
+
+/// Replacements of byte ranges of an input, e.g. ranges of spans produced by
+/// a cursor over it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Edits {
+    edits: Vec<(Range<usize>, String)>,
+}
+
+/// Why a set of edits can't be applied to an input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditError {
+    /// Two edits replace some of the same input.
+    Overlap {
+        first: Range<usize>,
+        second: Range<usize>,
+    },
+    /// An edit ends past the end of the input.
+    OutOfBounds { range: Range<usize> },
+    /// An edit starts or ends inside a char of the input.
+    NotCharBoundary { range: Range<usize> },
+}
+
+impl Edits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records replacing `range` of the input. Panics if the range is
+    /// inverted.
+    pub fn replace(&mut self, range: Range<usize>, replacement: impl Into<String>) -> &mut Self {
+        assert!(
+            range.start <= range.end,
+            "inverted edit range {}..{}",
+            range.start,
+            range.end
+        );
+        self.edits.push((range, replacement.into()));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Copy of `input` with every edit applied. Edits may be recorded in any
+    /// order, insertions at the same position are applied in the order they
+    /// were recorded. Nothing is applied if any edit doesn't fit `input`.
+    pub fn apply(&self, input: &str) -> Result<String, EditError> {
+        let mut edits: Vec<_> = self.edits.iter().collect();
+        edits.sort_by_key(|(range, _)| (range.start, range.end));
+
+        for (range, _) in &edits {
+            if range.end > input.len() {
+                return Err(EditError::OutOfBounds {
+                    range: range.clone(),
+                });
+            }
+            if !input.is_char_boundary(range.start) || !input.is_char_boundary(range.end) {
+                return Err(EditError::NotCharBoundary {
+                    range: range.clone(),
+                });
+            }
+        }
+
+        for pair in edits.windows(2) {
+            let (first, second) = (&pair[0].0, &pair[1].0);
+            if second.start < first.end {
+                return Err(EditError::Overlap {
+                    first: first.clone(),
+                    second: second.clone(),
+                });
+            }
+        }
+
+        let mut output = String::with_capacity(input.len());
+        let mut copied = 0;
+        for (range, replacement) in edits {
+            output.push_str(&input[copied..range.start]);
+            output.push_str(replacement);
+            copied = range.end;
+        }
+        output.push_str(&input[copied..]);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cursor;
+
+    #[test]
+    fn test_apply_edits() {
+        let input = "let x = foo(y);";
+        let mut cursor = Cursor::from(input);
+        cursor.consume_str("let ");
+        let name = cursor.focus_n(1);
+        cursor.focus_until_str("foo");
+        let call = cursor.focus_until_str("(");
+
+        let mut edits = Edits::new();
+        edits
+            .replace(
+                call.position()..call.position() + call.as_str().len(),
+                "bar",
+            )
+            .replace(name.position()..name.position() + 1, "value");
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits.apply(input).unwrap(), "let value = bar(y);");
+
+        let mut edits = Edits::new();
+        edits
+            .replace(4..5, "")
+            .replace(0..0, "/* */ ")
+            .replace(0..0, "// ");
+        assert_eq!(edits.apply(input).unwrap(), "/* */ // let  = foo(y);");
+    }
+
+    #[test]
+    fn test_apply_overlapping_edits() {
+        let mut edits = Edits::new();
+        edits.replace(6..10, "b").replace(2..8, "a");
+        assert_eq!(
+            edits.apply("0123456789"),
+            Err(EditError::Overlap {
+                first: 2..8,
+                second: 6..10,
+            })
+        );
+    }
+
+    #[test]
+    fn test_apply_edits_outside_input() {
+        let mut edits = Edits::new();
+        edits.replace(0..1, "a").replace(3..7, "b");
+        assert_eq!(
+            edits.apply("ы🦀"),
+            Err(EditError::NotCharBoundary { range: 0..1 })
+        );
+        assert_eq!(
+            edits.apply("abcd"),
+            Err(EditError::OutOfBounds { range: 3..7 })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "inverted edit range 3..1")]
+    fn test_inverted_edit() {
+        #[allow(clippy::reversed_empty_ranges)]
+        Edits::new().replace(3..1, "X");
+    }
+}
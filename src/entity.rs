@@ -0,0 +1,96 @@
+//! HTML character references like `&amp;` and `&#x41;`.
+
+use super::Cursor;
+
+/// Named references known to `consume_entity`.
+const NAMED: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{A0}'),
+];
+
+/// Why a `&` didn't start a valid character reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityError {
+    /// No `;` after the name or number.
+    Unterminated,
+    /// A name that isn't in the built-in table.
+    UnknownName,
+    /// Missing or invalid digits, or not a valid char.
+    InvalidNumber,
+}
+
+impl<'i> Cursor<'i> {
+    /// Consumes a character reference at a `&` and returns the char it
+    /// stands for, or `None` if the cursor isn't at a `&`. On error nothing
+    /// is consumed, so the `&` can be taken literally.
+    pub fn consume_entity(&mut self) -> Option<Result<char, EntityError>> {
+        if self.first() != Some('&') {
+            return None;
+        }
+
+        let mut cursor = self.clone();
+        cursor.consume();
+        let body = cursor.peek_while(|ch| ch == '#' || ch.is_ascii_alphanumeric());
+        cursor.consume_bytes(body.len());
+        if cursor.consume() != Some(';') {
+            return Some(Err(EntityError::Unterminated));
+        }
+
+        let resolved = match body.strip_prefix('#') {
+            Some(number) => {
+                let (digits, radix) = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => (hex, 16),
+                    None => (number, 10),
+                };
+                u32::from_str_radix(digits, radix)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or(EntityError::InvalidNumber)
+            }
+            None => NAMED
+                .iter()
+                .find(|(name, _)| *name == body)
+                .map(|&(_, ch)| ch)
+                .ok_or(EntityError::UnknownName),
+        };
+
+        if resolved.is_ok() {
+            *self = cursor;
+            self.check_invariants();
+        }
+        Some(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consume_entity() {
+        let mut cursor = Cursor::from("&amp;&#65;&#x41;&#X1F600; rest");
+        assert_eq!(cursor.consume_entity(), Some(Ok('&')));
+        assert_eq!(cursor.consume_entity(), Some(Ok('A')));
+        assert_eq!(cursor.consume_entity(), Some(Ok('A')));
+        assert_eq!(cursor.consume_entity(), Some(Ok('😀')));
+        assert_eq!(cursor.consume_entity(), None);
+        assert_eq!(cursor.as_str(), " rest");
+
+        let check = |input: &str, error| {
+            let mut cursor = Cursor::from(input);
+            assert_eq!(cursor.consume_entity(), Some(Err(error)), "{input:?}");
+            assert_eq!(cursor.position(), 0);
+        };
+        check("&bogus;", EntityError::UnknownName);
+        check("&amp", EntityError::Unterminated);
+        check("& x", EntityError::Unterminated);
+        check("&#;", EntityError::InvalidNumber);
+        check("&#x;", EntityError::InvalidNumber);
+        check("&#xD800;", EntityError::InvalidNumber);
+        check("&#12a;", EntityError::InvalidNumber);
+    }
+}
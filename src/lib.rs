@@ -1,40 +1,108 @@
-mod unescape;
+pub mod char_class;
+mod config;
+mod edits;
+mod entity;
+mod line_map;
+pub mod unescape;
 
-use std::ops::{Deref, DerefMut};
+pub use config::{CursorConfig, NewlineStyle};
+pub use edits::{EditError, Edits};
+pub use entity::EntityError;
+pub use line_map::LineMap;
+
+use char_class::{is_ident_continue, is_ident_start};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, DerefMut, Range};
 use std::str::Chars;
 
 #[derive(Debug, Clone)]
 pub struct Cursor<'i> {
     input: &'i str,
     chars: Chars<'i>,
+    config: CursorConfig,
 }
 
 impl<'i> From<&'i str> for Cursor<'i> {
     fn from(input: &'i str) -> Self {
-        Self {
-            input,
-            chars: input.chars(),
-        }
+        Self::with_config(input, CursorConfig::default())
+    }
+}
+
+impl Default for Cursor<'static> {
+    fn default() -> Self {
+        Cursor::from("")
     }
 }
 
 impl<'i> Cursor<'i> {
     pub fn new(input: &'i str, chars: Chars<'i>) -> Self {
-        #[cfg(debug_assertions)]
-        {
-            let start = chars.as_str().as_ptr() as usize;
-            let end = start + chars.as_str().len();
-            let input_start = input.as_ptr() as usize;
-            let input_end = input_start + input.len();
-            assert!(start >= input_start);
-            assert!(end <= input_end);
+        let cursor = Self {
+            input,
+            chars,
+            config: CursorConfig::default(),
+        };
+
+        #[cfg(any(debug_assertions, feature = "debug-invariants"))]
+        cursor.assert_within_input();
+
+        cursor
+    }
+
+    #[cfg(any(debug_assertions, feature = "debug-invariants"))]
+    fn assert_within_input(&self) {
+        let start = self.chars.as_str().as_ptr() as usize;
+        let end = start + self.chars.as_str().len();
+        let input_start = self.input.as_ptr() as usize;
+        let input_end = input_start + self.input.len();
+        assert!(start >= input_start);
+        assert!(end <= input_end);
+    }
+
+    #[inline(always)]
+    fn check_invariants(&self) {
+        #[cfg(feature = "debug-invariants")]
+        self.assert_within_input();
+    }
+
+    pub fn with_config(input: &'i str, config: CursorConfig) -> Self {
+        Self {
+            input,
+            chars: input.chars(),
+            config,
         }
+    }
 
-        Self { input, chars }
+    /// The config, which cursors focused from this one share.
+    pub fn config(&self) -> CursorConfig {
+        self.config
     }
 
     pub fn focus(&self, chars: Chars<'i>) -> Self {
-        Self::new(self.input, chars)
+        Self {
+            config: self.config,
+            ..Self::new(self.input, chars)
+        }
+    }
+
+    /// Cursor over `range` of `input`, regardless of this cursor's position.
+    /// Panics if the range is inverted or not on char boundaries.
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        self.focus(self.input[range].chars())
+    }
+
+    /// Rewinds to the start of `input`, which also lifts a `with_limit`.
+    pub fn reset(&mut self) {
+        self.chars = self.input.chars();
+    }
+
+    /// Cursor that only sees `input` up to the absolute `byte_limit`, as if
+    /// the input ended there. `input()` stays whole, so the limit can be
+    /// moved later, e.g. to `input().len()` once everything is available.
+    /// Panics if the limit is before the position or not on a char boundary.
+    pub fn with_limit(self, byte_limit: usize) -> Self {
+        let start = self.position();
+        self.focus(self.input[start..byte_limit].chars())
     }
 
     pub fn input(&self) -> &'i str {
@@ -52,6 +120,41 @@ impl<'i> Cursor<'i> {
     pub fn is_empty(&self) -> bool {
         self.as_str().is_empty()
     }
+
+    /// Remaining input as raw bytes.
+    ///
+    /// Consuming is still char based, so a byte index into this slice is only
+    /// meaningful to the cursor when it lies on a char boundary.
+    pub fn as_bytes(&self) -> &'i [u8] {
+        self.as_str().as_bytes()
+    }
+
+    /// Start and end pointers of the remaining input, for APIs taking raw
+    /// byte ranges. They are only valid to read through while the input is
+    /// borrowed for `'i`, and the bytes must not be written.
+    pub fn remaining_ptr_range(&self) -> Range<*const u8> {
+        self.as_bytes().as_ptr_range()
+    }
+
+    /// First byte of the remaining input, which is only a whole char when it
+    /// is ASCII.
+    pub fn peek_byte(&self) -> Option<u8> {
+        self.as_bytes().first().copied()
+    }
+}
+
+impl<'i> Cursor<'i> {
+    pub fn trimmed(&self) -> Self {
+        self.focus(self.as_str().trim().chars())
+    }
+
+    pub fn trimmed_start(&self) -> Self {
+        self.focus(self.as_str().trim_start().chars())
+    }
+
+    pub fn trimmed_end(&self) -> Self {
+        self.focus(self.as_str().trim_end().chars())
+    }
 }
 
 impl<'i> Cursor<'i> {
@@ -59,6 +162,32 @@ impl<'i> Cursor<'i> {
         self.as_str().as_ptr() as usize - self.input.as_ptr() as usize
     }
 
+    /// Like `position`, but `None` if the remaining chars don't lie within
+    /// `input`. That can only happen when neither `debug_assertions` nor the
+    /// `debug-invariants` feature is enabled, and `new` or `focus` was given
+    /// chars from another string, where `position` would return a
+    /// meaningless value.
+    pub fn position_checked(&self) -> Option<usize> {
+        let start = self.as_str().as_ptr() as usize;
+        let input_start = self.input.as_ptr() as usize;
+        let offset = start.checked_sub(input_start)?;
+        (offset + self.as_str().len() <= self.input.len()).then_some(offset)
+    }
+
+    /// Whether the cursor moved past the `before` position.
+    pub fn progressed_since(&self, before: usize) -> bool {
+        self.position() > before
+    }
+
+    /// Panics in debug builds if the cursor didn't move past the `before`
+    /// position, to catch lexer rules that would loop forever.
+    pub fn assert_progress(&self, before: usize) {
+        debug_assert!(
+            self.progressed_since(before),
+            "no progress past position {before}"
+        );
+    }
+
     pub fn previous(&self) -> char {
         self.input[0..self.position()]
             .chars()
@@ -75,15 +204,182 @@ impl<'i> Cursor<'i> {
         chars.next();
         chars.next()
     }
+
+    /// The text `focus_while` would focus, without consuming it.
+    pub fn peek_while(&self, predicate: impl FnMut(char) -> bool) -> &'i str {
+        self.clone().focus_while(predicate).as_str()
+    }
+
+    /// Remaining input after skipping `char_count` chars, or `""` past the end.
+    pub fn rest_after(&self, char_count: usize) -> &'i str {
+        let mut chars = self.chars();
+        for _ in chars.by_ref().take(char_count) {}
+        chars.as_str()
+    }
+
+    /// Remaining chars with their byte offsets into `input`, unlike
+    /// `str::char_indices` which is relative to `as_str()`.
+    pub fn char_indices(&self) -> impl Iterator<Item = (usize, char)> + 'i {
+        let position = self.position();
+        self.as_str()
+            .char_indices()
+            .map(move |(offset, ch)| (position + offset, ch))
+    }
+
+    /// Offsets into `input` of each non-overlapping `pat` in the remaining
+    /// input.
+    pub fn match_positions<'a>(&self, pat: &'a str) -> impl Iterator<Item = usize> + 'a
+    where
+        'i: 'a,
+    {
+        let position = self.position();
+        self.as_str()
+            .match_indices(pat)
+            .map(move |(offset, _)| position + offset)
+    }
+
+    /// Byte offset into `input` of the char numbered `char_index`, or `None`
+    /// if there are not that many chars.
+    pub fn byte_offset_of_char(&self, char_index: usize) -> Option<usize> {
+        self.input
+            .char_indices()
+            .nth(char_index)
+            .map(|(offset, _)| offset)
+    }
+
+    /// Largest char boundary of `input` at or before `byte_pos`.
+    pub fn floor_char_boundary(&self, byte_pos: usize) -> usize {
+        let mut byte_pos = byte_pos.min(self.input.len());
+        while !self.input.is_char_boundary(byte_pos) {
+            byte_pos -= 1;
+        }
+        byte_pos
+    }
+
+    /// Smallest char boundary of `input` at or after `byte_pos`, clamped to
+    /// the end of input.
+    pub fn ceil_char_boundary(&self, byte_pos: usize) -> usize {
+        let mut byte_pos = byte_pos.min(self.input.len());
+        while !self.input.is_char_boundary(byte_pos) {
+            byte_pos += 1;
+        }
+        byte_pos
+    }
+
+    /// Number of line endings consumed so far, `\n` unless configured
+    /// otherwise. This is O(position).
+    pub fn consumed_line_count(&self) -> usize {
+        let consumed = &self.input[..self.position()];
+        match self.config.newline.terminator() {
+            "\n" => consumed.bytes().filter(|&b| b == b'\n').count(),
+            terminator => consumed.matches(terminator).count(),
+        }
+    }
+
+    /// Zero based line and char column, with tabs advancing to the next
+    /// multiple of the configured tab width. This is O(position), see
+    /// `LineMap` for repeated lookups.
+    pub fn line_col(&self) -> (usize, usize) {
+        let col = self.config.columns(self.consumed_in_line());
+        (self.consumed_line_count(), col)
+    }
+
+    fn consumed_in_line(&self) -> &'i str {
+        let consumed = &self.input[..self.position()];
+        let terminator = self.config.newline.terminator();
+        let line_start = consumed
+            .rfind(terminator)
+            .map_or(0, |newline| newline + terminator.len());
+        &consumed[line_start..]
+    }
+
+    /// The whole line of `input` around `byte_pos`, without its line ending,
+    /// like `lines` would yield it. A position in a line ending is on the
+    /// line that it ends. Panics if `byte_pos` is past the end of the input
+    /// or not on a char boundary.
+    pub fn line_containing(&self, byte_pos: usize) -> &'i str {
+        let terminator = self.config.newline.terminator();
+        let start = self.input[..byte_pos]
+            .rfind(terminator)
+            .map_or(0, |newline| newline + terminator.len());
+        let end = self.input[start..]
+            .find(terminator)
+            .map_or(self.input.len(), |newline| {
+                start + newline + terminator.len()
+            });
+        self.config.newline.strip(&self.input[start..end])
+    }
+
+    /// Position in UTF-16 code units, as used by LSP. This is O(position).
+    pub fn utf16_position(&self) -> usize {
+        self.input[..self.position()]
+            .chars()
+            .map(char::len_utf16)
+            .sum()
+    }
+
+    /// Zero based line and UTF-16 column, as used by LSP. This is
+    /// O(position).
+    pub fn utf16_line_col(&self) -> (usize, usize) {
+        let col = self.consumed_in_line().chars().map(char::len_utf16).sum();
+        (self.consumed_line_count(), col)
+    }
+
+    /// Whether both cursors are over the very same input, which `span_from`
+    /// and other methods combining two cursors require. They only check this
+    /// in debug builds, so call this first when it isn't known to hold.
+    pub fn same_input(&self, other: &Cursor<'i>) -> bool {
+        std::ptr::eq(self.input.as_ptr(), other.input.as_ptr())
+            && self.input.len() == other.input.len()
+    }
+
+    /// Byte range of `input` consumed between the `start` checkpoint and now.
+    pub fn span_from(&self, start: &Cursor<'i>) -> Range<usize> {
+        Self::range_between(start, self)
+    }
+
+    /// All arithmetic between positions of two cursors goes through here, so
+    /// that mixing up cursors over different inputs is caught in debug builds
+    /// instead of producing a wrapped-around range.
+    fn range_between(start: &Cursor<'i>, end: &Cursor<'i>) -> Range<usize> {
+        debug_assert!(start.same_input(end), "cursors over different inputs");
+        let range = start.position()..end.position();
+        debug_assert!(range.start <= range.end, "cursors out of order");
+        range
+    }
+
+    /// Byte offset of this cursor's `input` within `outer`'s, or `None` if it
+    /// doesn't lie within it, e.g. when the cursors are over unrelated
+    /// strings.
+    fn input_offset_in(&self, outer: &Cursor<'i>) -> Option<usize> {
+        let start = self.input.as_ptr() as usize;
+        let offset = start.checked_sub(outer.input.as_ptr() as usize)?;
+        (offset + self.input.len() <= outer.input.len()).then_some(offset)
+    }
+
+    /// Empty cursor where the remaining chars end.
+    fn end(&self) -> Self {
+        let rest = self.as_str();
+        self.focus(rest[rest.len()..].chars())
+    }
 }
 
 impl<'i> Cursor<'i> {
     pub fn consume(&mut self) -> Option<char> {
-        self.chars.next()
+        let ch = self.chars.next();
+        self.check_invariants();
+        ch
+    }
+
+    fn consume_bytes(&mut self, len: usize) -> &mut Self {
+        self.chars = self.as_str()[len..].chars();
+        self.check_invariants();
+        self
     }
 
     pub fn consume_with(&mut self, mut func: impl FnMut(&mut Cursor<'i>)) -> &mut Self {
         func(self);
+        self.check_invariants();
         self
     }
 
@@ -99,6 +395,227 @@ impl<'i> Cursor<'i> {
         })
     }
 
+    pub fn consume_n(&mut self, n: usize) -> &mut Self {
+        self.consume_with(|cursor| {
+            for _ in 0..n {
+                cursor.consume();
+            }
+        })
+    }
+
+    pub fn consume_str(&mut self, expected: &str) -> bool {
+        if self.as_str().starts_with(expected) {
+            self.consume_bytes(expected.len());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Like `consume_str`, but ASCII letters match regardless of case.
+    pub fn consume_str_ignore_ascii_case(&mut self, prefix: &str) -> bool {
+        let matches = self
+            .as_bytes()
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix.as_bytes()));
+        if matches {
+            self.consume_bytes(prefix.len());
+        }
+        matches
+    }
+
+    /// Consumes the longest of `alternatives` the input starts with and
+    /// returns it, the first one listed on ties. Their order doesn't matter
+    /// otherwise, so `<` can come before `<=`.
+    pub fn consume_any_str<'a>(&mut self, alternatives: &'a [&'a str]) -> Option<&'a str> {
+        let rest = self.as_str();
+        let longest = alternatives
+            .iter()
+            .filter(|alternative| rest.starts_with(**alternative))
+            .min_by_key(|alternative| std::cmp::Reverse(alternative.len()))?;
+        self.consume_bytes(longest.len());
+        Some(longest)
+    }
+
+    /// Like `consume_str`, but returns the consumed slice of the input.
+    pub fn consume_prefix(&mut self, prefix: &str) -> Option<&'i str> {
+        let rest = self.as_str();
+        self.consume_str(prefix).then(|| &rest[..prefix.len()])
+    }
+
+    /// Like `consume_str`, but reports what was found instead on a mismatch.
+    /// The preview is as many chars as `expected` has, cut at a newline.
+    pub fn expect_str(&mut self, expected: &str) -> Result<(), CursorError<'i>> {
+        if self.consume_str(expected) {
+            return Ok(());
+        }
+
+        let preview_len = expected.chars().count().max(1);
+        let found = self
+            .clone()
+            .focus_while_indexed(|index, ch| index < preview_len && ch != '\n')
+            .as_str();
+        Err(CursorError {
+            position: self.position(),
+            expected: expected.to_string(),
+            found,
+        })
+    }
+
+    pub fn consume_if(&mut self, predicate: impl FnOnce(char) -> bool) -> Option<char> {
+        let ch = self.first().filter(|&ch| predicate(ch))?;
+        self.consume();
+        Some(ch)
+    }
+
+    pub fn consume_one_of(&mut self, set: &[char]) -> Option<char> {
+        self.consume_if(|ch| set.contains(&ch))
+    }
+
+    pub fn consume_one_of_str(&mut self, set: &str) -> Option<char> {
+        self.consume_if(|ch| set.contains(ch))
+    }
+
+    /// Like `consume_while`, but the predicate also gets the index of the char
+    /// within the run being consumed.
+    pub fn consume_while_indexed(
+        &mut self,
+        mut predicate: impl FnMut(usize, char) -> bool,
+    ) -> &mut Self {
+        self.consume_with(|cursor| {
+            for (index, ch) in cursor.chars().enumerate() {
+                if predicate(index, ch) {
+                    cursor.consume();
+                } else {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Like `consume_while`, but the predicate also sees the char after the
+    /// candidate.
+    pub fn consume_while_lookahead(
+        &mut self,
+        mut predicate: impl FnMut(char, Option<char>) -> bool,
+    ) -> &mut Self {
+        self.consume_with(|cursor| {
+            while let Some(ch) = cursor.first() {
+                if predicate(ch, cursor.second()) {
+                    cursor.consume();
+                } else {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Like `consume_while`, but for byte predicates, skipping char decoding.
+    /// Stops at the first non-ASCII char, which an ASCII predicate can't
+    /// match anyway.
+    pub fn consume_while_ascii(&mut self, predicate: impl Fn(u8) -> bool) -> &mut Self {
+        let len = self
+            .as_bytes()
+            .iter()
+            .position(|&byte| !byte.is_ascii() || !predicate(byte))
+            .unwrap_or(self.as_bytes().len());
+        self.consume_bytes(len)
+    }
+
+    /// Like `consume_while`, but an `escape` and the char after it are
+    /// consumed without asking the predicate.
+    pub fn consume_while_escaped(
+        &mut self,
+        escape: char,
+        mut predicate: impl FnMut(char) -> bool,
+    ) -> &mut Self {
+        self.consume_with(|cursor| {
+            while let Some(ch) = cursor.first() {
+                if ch == escape {
+                    cursor.consume_n(2);
+                } else if predicate(ch) {
+                    cursor.consume();
+                } else {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Like `consume_while`, but the predicate also sees the cursor, still
+    /// positioned before the candidate, for arbitrary lookahead. It only gets
+    /// a shared reference, so it can't move the cursor from under the loop.
+    pub fn consume_while_with_cursor(
+        &mut self,
+        mut predicate: impl FnMut(&Cursor<'i>, char) -> bool,
+    ) -> &mut Self {
+        self.consume_with(|cursor| {
+            while let Some(ch) = cursor.first() {
+                if predicate(cursor, ch) {
+                    cursor.consume();
+                } else {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Like `consume_while`, but fails without consuming anything if the
+    /// predicate accepts a `\n`, i.e. the token would continue onto the next
+    /// line.
+    #[allow(clippy::result_unit_err)]
+    pub fn consume_while_inline(
+        &mut self,
+        mut predicate: impl FnMut(char) -> bool,
+    ) -> Result<&mut Self, ()> {
+        let mut cursor = self.clone();
+        while let Some(ch) = cursor.first() {
+            if !predicate(ch) {
+                break;
+            }
+            if ch == '\n' {
+                return Err(());
+            }
+            cursor.consume();
+        }
+        *self = cursor;
+        self.check_invariants();
+        Ok(self)
+    }
+
+    /// Like `consume_while`, but consumes at most `max` chars. Returns how
+    /// many were consumed and whether it stopped at `max` with the predicate
+    /// still holding for the next char.
+    pub fn consume_while_bounded(
+        &mut self,
+        max: usize,
+        mut predicate: impl FnMut(char) -> bool,
+    ) -> (usize, bool) {
+        let mut count = 0;
+        while let Some(ch) = self.first() {
+            if !predicate(ch) {
+                return (count, false);
+            }
+            if count == max {
+                return (count, true);
+            }
+            self.consume();
+            count += 1;
+        }
+        (count, false)
+    }
+
+    /// Consumes up to `max` repetitions of `ch` and returns how many, or
+    /// consumes nothing and returns `None` if there are fewer than `min`.
+    pub fn consume_repeated(&mut self, ch: char, min: usize, max: usize) -> Option<usize> {
+        let count = self.chars().take(max).take_while(|&c| c == ch).count();
+        if count < min {
+            return None;
+        }
+        self.consume_bytes(count * ch.len_utf8());
+        Some(count)
+    }
+
     pub fn consume_until(&mut self, mut predicate: impl FnMut(char) -> bool) -> &mut Self {
         self.consume_with(|cursor| {
             for ch in cursor.chars() {
@@ -112,11 +629,86 @@ impl<'i> Cursor<'i> {
     }
 }
 
+impl<'i> Cursor<'i> {
+    /// Consumes through the first char in `stops` and returns it, or consumes
+    /// the rest of the input and returns `None`.
+    pub fn consume_until_any(&mut self, stops: &[char]) -> Option<char> {
+        let mut stop = None;
+        self.consume_until(|ch| {
+            stop = Some(ch).filter(|ch| stops.contains(ch));
+            stop.is_some()
+        });
+        stop
+    }
+}
+
 impl<'i> Cursor<'i> {
     pub fn consume_line(&mut self) -> &mut Self {
         self.consume_until(|ch| ch == '\n')
     }
 
+    /// Consumes a `\r\n` or `\n` line ending, if there is one.
+    pub fn consume_crlf(&mut self) -> bool {
+        match (self.first(), self.second()) {
+            (Some('\n'), _) => {
+                self.consume();
+                true
+            }
+            (Some('\r'), Some('\n')) => {
+                self.consume();
+                self.consume();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Consumes all leading whitespace and returns how many `\n` were in it.
+    pub fn skip_ws_count_newlines(&mut self) -> usize {
+        let mut newlines = 0;
+        self.consume_while(|ch| {
+            newlines += usize::from(ch == '\n');
+            ch.is_whitespace()
+        });
+        newlines
+    }
+
+    /// Consumes leading spaces and tabs and returns their width, with tabs
+    /// advancing to the next multiple of the configured tab width. With
+    /// `strict_indent` configured, mixing both is an error and nothing is
+    /// consumed.
+    pub fn measure_indent(&mut self) -> Result<usize, IndentError> {
+        let tab_width = self.config.tab_width.max(1);
+        let first = self.first();
+        let mut width = 0;
+        let mut cursor = self.clone();
+        while let Some(ch @ (' ' | '\t')) = cursor.first() {
+            if self.config.strict_indent && Some(ch) != first {
+                return Err(IndentError {
+                    position: cursor.position(),
+                });
+            }
+            width = match ch {
+                '\t' => width + tab_width - width % tab_width,
+                _ => width + 1,
+            };
+            cursor.consume();
+        }
+        *self = cursor;
+        self.check_invariants();
+        Ok(width)
+    }
+
+    /// Consumes a UTF-8 byte order mark at the very start of the input.
+    pub fn skip_bom(&mut self) -> bool {
+        if self.position() == 0 && self.first() == Some('\u{FEFF}') {
+            self.consume();
+            true
+        } else {
+            false
+        }
+    }
+
     pub fn consume_lines_while(&mut self, mut predicate: impl FnMut(&'i str) -> bool) -> &mut Self {
         self.consume_with(|cursor| {
             for next_line in cursor.chars().as_str().lines() {
@@ -147,7 +739,7 @@ impl<'i> Cursor<'i> {
         let start = self.position();
         func(self);
         let end = self.position();
-        Self::new(self.input, self.input[start..end].chars())
+        self.slice(start..end)
     }
 
     pub fn focus_char(&mut self) -> Self {
@@ -156,24 +748,106 @@ impl<'i> Cursor<'i> {
         })
     }
 
+    pub fn focus_n(&mut self, n: usize) -> Self {
+        self.focus_with(|cursor| {
+            cursor.consume_n(n);
+        })
+    }
+
     pub fn focus_line(&mut self) -> Self {
         self.focus_with(|cursor| {
             cursor.consume_line();
         })
     }
 
+    /// Focuses the rest of the input, leaving this cursor at the end.
+    pub fn focus_rest(&mut self) -> Self {
+        let rest = self.chars.clone();
+        self.consume_bytes(rest.as_str().len());
+        self.focus(rest)
+    }
+
     pub fn focus_while(&mut self, mut predicate: impl FnMut(char) -> bool) -> Self {
         self.focus_with(|cursor| {
             cursor.consume_while(&mut predicate);
         })
     }
 
+    pub fn focus_while_indexed(&mut self, mut predicate: impl FnMut(usize, char) -> bool) -> Self {
+        self.focus_with(|cursor| {
+            cursor.consume_while_indexed(&mut predicate);
+        })
+    }
+
+    /// Focuses chars while `f` folds them into the accumulator, stopping
+    /// before the first char it returns `None` for. `f` gets a clone of the
+    /// accumulator so that it survives that last call.
+    pub fn fold_while<A: Clone>(
+        &mut self,
+        init: A,
+        mut f: impl FnMut(A, char) -> Option<A>,
+    ) -> (A, Self) {
+        let mut acc = init;
+        let focused = self.focus_while(|ch| match f(acc.clone(), ch) {
+            Some(next) => {
+                acc = next;
+                true
+            }
+            None => false,
+        });
+        (acc, focused)
+    }
+
     pub fn focus_until(&mut self, mut predicate: impl FnMut(char) -> bool) -> Self {
         self.focus_with(|cursor| {
             cursor.consume_until(&mut predicate);
         })
     }
 
+    /// Focuses everything before the first char in `stops`, leaving the
+    /// cursor at it. Focuses the rest of the input if there is none.
+    pub fn focus_until_any(&mut self, stops: &[char]) -> Self {
+        self.focus_while(|ch| !stops.contains(&ch))
+    }
+
+    /// Focuses a balanced region without its delimiters. Same as
+    /// `consume_balanced`.
+    pub fn focus_balanced_inner(
+        &mut self,
+        open: char,
+        close: char,
+    ) -> Result<Self, UnbalancedError> {
+        self.consume_balanced(open, close)
+    }
+
+    /// Focuses a balanced region including its delimiters.
+    pub fn focus_balanced_outer(
+        &mut self,
+        open: char,
+        close: char,
+    ) -> Result<Self, UnbalancedError> {
+        let start = self.position();
+        self.consume_balanced(open, close)?;
+        Ok(self.slice(start..self.position()))
+    }
+
+    /// Focuses up to the first `close` without a matching `open`, leaving
+    /// the cursor at it. Focuses the rest of the input if there is none.
+    pub fn focus_until_unbalanced(&mut self, open: char, close: char) -> Self {
+        let mut depth = 0usize;
+        self.focus_while(|ch| {
+            if ch == open {
+                depth += 1;
+            } else if ch == close {
+                if depth == 0 {
+                    return false;
+                }
+                depth -= 1;
+            }
+            true
+        })
+    }
+
     pub fn focus_lines_while(&mut self, mut predicate: impl FnMut(&'i str) -> bool) -> Self {
         self.focus_with(|cursor| {
             cursor.consume_lines_while(&mut predicate);
@@ -185,37 +859,811 @@ impl<'i> Cursor<'i> {
             cursor.consume_lines_until(&mut predicate);
         })
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct Span<'i, Kind: 'i> {
-    pub kind: Kind,
-    pub cursor: Cursor<'i>,
-}
+    /// Focuses lines up to the first one matching `predicate`. With
+    /// `inclusive` the matching line is focused and consumed too, otherwise
+    /// the cursor stops at its start.
+    pub fn focus_block_until(
+        &mut self,
+        mut predicate: impl FnMut(&'i str) -> bool,
+        inclusive: bool,
+    ) -> Self {
+        if inclusive {
+            self.focus_lines_until(predicate)
+        } else {
+            self.focus_lines_while(|line| !predicate(line))
+        }
+    }
 
-impl<'i, Kind: 'i> Span<'i, Kind> {
-    pub fn new(kind: Kind, cursor: Cursor<'i>) -> Self {
-        Self { kind, cursor }
+    /// Consumes through the first `terminator` not preceded by `escape`,
+    /// returning what came before it. `escape` always takes the following char
+    /// with it, even at the end of input. Without a terminator the rest of the
+    /// input is returned.
+    pub fn consume_escaped_until(&mut self, terminator: char, escape: char) -> Self {
+        let content = self.consume_until_unescaped(&[terminator], escape);
+        self.consume();
+        content
     }
 
-    pub fn to_kind<OtherKind: 'i>(&self, other_kind: OtherKind) -> Span<'i, OtherKind> {
-        Span::new(other_kind, self.cursor.clone())
+    /// Consumes up to, but not including, the first char in `stops` not
+    /// preceded by `escape`, and returns what was consumed. `escape` always
+    /// takes the following char with it.
+    pub fn consume_until_unescaped(&mut self, stops: &[char], escape: char) -> Self {
+        self.focus_with(|cursor| {
+            while let Some(ch) = cursor.first() {
+                if stops.contains(&ch) {
+                    break;
+                }
+                cursor.consume();
+                if ch == escape {
+                    cursor.consume();
+                }
+            }
+        })
     }
-}
 
-impl<'i, Kind: 'i> Deref for Span<'i, Kind> {
-    type Target = Cursor<'i>;
+    /// Consumes from an `open` char through its matching `close`, returning
+    /// the region between them. On error nothing is consumed.
+    pub fn consume_balanced(&mut self, open: char, close: char) -> Result<Self, UnbalancedError> {
+        let open_position = self.position();
+        if self.first() != Some(open) {
+            return Err(UnbalancedError {
+                open_position,
+                depth: 0,
+            });
+        }
 
-    fn deref(&self) -> &Self::Target {
-        &self.cursor
+        let mut cursor = self.clone();
+        cursor.consume();
+        let start = cursor.position();
+        let mut depth = 1;
+
+        loop {
+            let end = cursor.position();
+            match cursor.consume() {
+                Some(ch) if ch == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        *self = cursor;
+                        self.check_invariants();
+                        return Ok(self.slice(start..end));
+                    }
+                }
+                Some(ch) if ch == open => depth += 1,
+                Some(_) => {}
+                None => {
+                    return Err(UnbalancedError {
+                        open_position,
+                        depth,
+                    })
+                }
+            }
+        }
     }
-}
 
-impl<'i, Kind: 'i> DerefMut for Span<'i, Kind> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.cursor
+    /// Like `consume_balanced`, but returns the opener, the region between and
+    /// the closer as separate spans.
+    pub fn consume_delimited(
+        &mut self,
+        open: char,
+        close: char,
+    ) -> Option<(Span<'i, ()>, Span<'i, ()>, Span<'i, ()>)> {
+        let start = self.position();
+        let inner = self.consume_balanced(open, close).ok()?;
+        let inner_end = inner.position() + inner.as_str().len();
+        let opener = self.slice(start..inner.position());
+        let closer = self.slice(inner_end..self.position());
+        Some((
+            Span::new((), opener),
+            Span::new((), inner),
+            Span::new((), closer),
+        ))
     }
-}
+
+    /// Like `consume_balanced`, but `escape` makes the following char literal,
+    /// so escaped delimiters don't change the depth. The escapes are kept in
+    /// the returned region. On error nothing is consumed.
+    pub fn consume_balanced_escaped(
+        &mut self,
+        open: char,
+        close: char,
+        escape: char,
+    ) -> Option<Self> {
+        if self.first() != Some(open) {
+            return None;
+        }
+
+        let mut cursor = self.clone();
+        cursor.consume();
+        let start = cursor.position();
+        let mut depth = 1;
+
+        loop {
+            let end = cursor.position();
+            match cursor.consume()? {
+                ch if ch == escape => {
+                    cursor.consume();
+                }
+                ch if ch == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        *self = cursor;
+                        self.check_invariants();
+                        return Some(self.slice(start..end));
+                    }
+                }
+                ch if ch == open => depth += 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Consumes from an `open` delimiter through its matching `close`,
+    /// returning the region between them. On imbalance, or if the cursor isn't
+    /// at `open`, nothing is consumed.
+    ///
+    /// At every position `close` is tried before `open`, so identical or
+    /// overlapping delimiters close rather than nest.
+    pub fn consume_balanced_str(&mut self, open: &str, close: &str) -> Option<Self> {
+        if open.is_empty() || close.is_empty() || !self.as_str().starts_with(open) {
+            return None;
+        }
+
+        let mut cursor = self.clone();
+        cursor.consume_bytes(open.len());
+        let start = cursor.position();
+        let mut depth = 1;
+
+        loop {
+            let rest = cursor.as_str();
+            if rest.starts_with(close) {
+                depth -= 1;
+                if depth == 0 {
+                    let inner = self.slice(start..cursor.position());
+                    cursor.consume_bytes(close.len());
+                    *self = cursor;
+                    self.check_invariants();
+                    return Some(inner);
+                }
+                cursor.consume_bytes(close.len());
+            } else if rest.starts_with(open) {
+                depth += 1;
+                cursor.consume_bytes(open.len());
+            } else {
+                cursor.consume()?;
+            }
+        }
+    }
+
+    /// Splits the remaining input at every `sep` outside of `open`/`close`
+    /// pairs, like `str::split`. A stray `close` doesn't go below depth 0.
+    pub fn split_top_level(&self, sep: char, open: char, close: char) -> Vec<Span<'i, ()>> {
+        let mut segments = vec![];
+        let mut start = self.position();
+        let mut depth = 0usize;
+        for (position, ch) in self.char_indices() {
+            if ch == open {
+                depth += 1;
+            } else if ch == close {
+                depth = depth.saturating_sub(1);
+            } else if ch == sep && depth == 0 {
+                segments.push(Span::new((), self.slice(start..position)));
+                start = position + ch.len_utf8();
+            }
+        }
+        segments.push(Span::new(
+            (),
+            self.slice(start..self.position() + self.as_str().len()),
+        ));
+        segments
+    }
+
+    /// Focuses everything before the first `terminator`, leaving the cursor at
+    /// the terminator. Focuses the rest of the input if it is never found.
+    pub fn focus_until_str(&mut self, terminator: &str) -> Self {
+        self.focus_with(|cursor| {
+            let len = cursor
+                .as_str()
+                .find(terminator)
+                .unwrap_or(cursor.as_str().len());
+            cursor.consume_bytes(len);
+        })
+    }
+}
+
+impl<'i> Cursor<'i> {
+    /// Iterates the remaining lines without their `\n` or `\r\n`, numbered
+    /// from 0. Lines end as the config's `newline` says.
+    pub fn lines(self) -> Lines<'i> {
+        Lines::new(self, false)
+    }
+
+    /// Iterates the remaining lines including their `\n`, numbered from 0.
+    pub fn lines_inclusive(self) -> Lines<'i> {
+        Lines::new(self, true)
+    }
+
+    /// Iterates runs of consecutive non-blank lines, without the line ending
+    /// of the last one. Whitespace-only lines count as blank.
+    pub fn paragraphs(self) -> impl Iterator<Item = Span<'i, ()>> {
+        let cursor = self.clone();
+        let is_blank = |line: &Span<'i, ()>| line.as_str().trim().is_empty();
+        let mut lines = self.lines().map(|(_, line)| line).peekable();
+        std::iter::from_fn(move || {
+            let first = lines.by_ref().find(|line| !is_blank(line))?;
+            let mut last = first.clone();
+            while let Some(line) = lines.next_if(|line| !is_blank(line)) {
+                last = line;
+            }
+            let end = last.position() + last.as_str().len();
+            Some(Span::new((), cursor.slice(first.position()..end)))
+        })
+    }
+
+    /// The next line without its line ending, like `lines` would yield it,
+    /// without consuming it.
+    pub fn peek_line(&self) -> &'i str {
+        let line = self.clone().focus_configured_line();
+        self.config.newline.strip(line.as_str())
+    }
+
+    /// Like `focus_line`, but through the configured line ending.
+    fn focus_configured_line(&mut self) -> Self {
+        let terminator = self.config.newline.terminator();
+        self.focus_with(|cursor| {
+            let rest = cursor.as_str();
+            let len = rest
+                .find(terminator)
+                .map_or(rest.len(), |end| end + terminator.len());
+            cursor.consume_bytes(len);
+        })
+    }
+}
+
+impl<'i> Cursor<'i> {
+    /// Copy of the remaining input with up to `n` leading whitespace chars
+    /// removed from each line. Line endings are kept.
+    pub fn dedent(&self, n: usize) -> String {
+        let mut dedented = String::with_capacity(self.as_str().len());
+        for line in self.as_str().split_inclusive('\n') {
+            let mut line = Cursor::from(line);
+            line.consume_while_indexed(|index, ch| {
+                index < n && ch.is_whitespace() && ch != '\n' && ch != '\r'
+            });
+            dedented.push_str(line.as_str());
+        }
+        dedented
+    }
+}
+
+impl<'i> Cursor<'i> {
+    /// Iterator consuming chars from this cursor. Whatever isn't iterated
+    /// stays in the cursor.
+    ///
+    /// `Cursor` isn't an `Iterator` itself, as `Iterator::position` would then
+    /// shadow `Cursor::position` on `&mut Cursor`.
+    pub fn drain(&mut self) -> Drain<'_, 'i> {
+        Drain { cursor: self }
+    }
+}
+
+#[derive(Debug)]
+pub struct Drain<'c, 'i> {
+    cursor: &'c mut Cursor<'i>,
+}
+
+impl<'c, 'i> Iterator for Drain<'c, 'i> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        self.cursor.consume()
+    }
+}
+
+impl<'i> IntoIterator for Cursor<'i> {
+    type Item = char;
+    type IntoIter = Chars<'i>;
+
+    fn into_iter(self) -> Chars<'i> {
+        self.chars
+    }
+}
+
+impl<'c, 'i> IntoIterator for &'c mut Cursor<'i> {
+    type Item = char;
+    type IntoIter = Drain<'c, 'i>;
+
+    fn into_iter(self) -> Drain<'c, 'i> {
+        self.drain()
+    }
+}
+
+impl<'i> Cursor<'i> {
+    pub fn take(&mut self, n: usize) -> Span<'i, ()> {
+        Span::new((), self.focus_n(n))
+    }
+
+    pub fn take_while(&mut self, predicate: impl FnMut(char) -> bool) -> Span<'i, ()> {
+        Span::new((), self.focus_while(predicate))
+    }
+
+    pub fn take_until(&mut self, predicate: impl FnMut(char) -> bool) -> Span<'i, ()> {
+        Span::new((), self.focus_until(predicate))
+    }
+
+    /// Skips whitespace and focuses the run of non-whitespace chars after
+    /// it, or returns `None` if only whitespace is left.
+    pub fn focus_word(&mut self) -> Option<Span<'i, ()>> {
+        self.consume_while(char::is_whitespace);
+        let word = self.take_while(|ch| !ch.is_whitespace());
+        (!word.is_empty()).then_some(word)
+    }
+}
+
+impl<'i> Cursor<'i> {
+    /// Focuses an identifier, by XID rules with the `unicode-ident` feature
+    /// and ASCII letters, digits and `_` otherwise.
+    pub fn focus_identifier(&mut self) -> Option<Span<'i, ()>> {
+        if !self.first().is_some_and(is_ident_start) {
+            return None;
+        }
+
+        let identifier = self.focus_while_indexed(|index, ch| match index {
+            0 => is_ident_start(ch),
+            _ => is_ident_continue(ch),
+        });
+        Some(Span::new((), identifier))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    Line,
+    Block,
+}
+
+impl<'i> Cursor<'i> {
+    /// Consumes a `//` comment up to, but not including, the newline, or a
+    /// nested `/* */` comment. An unterminated block comment consumes the
+    /// rest of the input and is still a `Block`.
+    pub fn consume_comment(&mut self) -> Option<CommentKind> {
+        if self.consume_str("//") {
+            self.consume_while(|ch| ch != '\n');
+            return Some(CommentKind::Line);
+        }
+
+        if !self.consume_str("/*") {
+            return None;
+        }
+
+        let mut depth = 1;
+        while depth > 0 && !self.is_empty() {
+            if self.consume_str("/*") {
+                depth += 1;
+            } else if self.consume_str("*/") {
+                depth -= 1;
+            } else {
+                self.consume();
+            }
+        }
+        Some(CommentKind::Block)
+    }
+
+    /// Consumes a `#!` line at the start of the input, or right after a BOM,
+    /// up to the newline. Like rustc, a `#!` whose next token after
+    /// whitespace and comments is `[` is an inner attribute and stays.
+    pub fn skip_shebang(&mut self) -> bool {
+        if !matches!(&self.input[..self.position()], "" | "\u{FEFF}") {
+            return false;
+        }
+
+        let mut tail = self.clone();
+        if !tail.consume_str("#!") {
+            return false;
+        }
+        let line_end = tail.clone().consume_while(|ch| ch != '\n').position();
+        loop {
+            tail.consume_while(char::is_whitespace);
+            if tail.consume_comment().is_none() {
+                break;
+            }
+        }
+        if tail.first() == Some('[') {
+            return false;
+        }
+
+        self.consume_bytes(line_end - self.position());
+        true
+    }
+}
+
+/// Why `consume_raw_string` failed. Nothing is consumed either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawStringError {
+    /// The cursor wasn't at `r`, optional `#`s and a `"`.
+    NotRawString,
+    /// No `"` followed by `hashes` `#`s closed the string.
+    Unterminated { hashes: usize },
+}
+
+impl<'i> Cursor<'i> {
+    /// Consumes a Rust style raw string like `r#"a "quoted" b"#` and returns
+    /// its contents, tagged with the number of `#`s around them.
+    pub fn consume_raw_string(&mut self) -> Result<Span<'i, usize>, RawStringError> {
+        let mut cursor = self.clone();
+        if cursor.consume() != Some('r') {
+            return Err(RawStringError::NotRawString);
+        }
+        let hashes = cursor.peek_while(|ch| ch == '#').len();
+        cursor.consume_bytes(hashes);
+        if cursor.consume() != Some('"') {
+            return Err(RawStringError::NotRawString);
+        }
+
+        let closing = format!("\"{}", "#".repeat(hashes));
+        let start = cursor.position();
+        let len = cursor
+            .as_str()
+            .find(&closing)
+            .ok_or(RawStringError::Unterminated { hashes })?;
+        let contents = cursor.slice(start..start + len);
+        cursor.consume_bytes(len + closing.len());
+
+        *self = cursor;
+        self.check_invariants();
+        Ok(Span::new(hashes, contents))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberKind {
+    Int,
+    Float,
+}
+
+impl<'i> Cursor<'i> {
+    /// Focuses the longest number at the cursor: an optional sign, digits, an
+    /// optional fraction and an optional exponent. A `.` or exponent is only
+    /// part of the number when digits follow it, so `1.` and `1e` are `Int`s
+    /// followed by the rest. Returns `None` without consuming if there are no
+    /// leading digits, e.g. for `.5`.
+    pub fn focus_number(&mut self) -> Option<Span<'i, NumberKind>> {
+        let is_digit = |ch: char| ch.is_ascii_digit();
+        let start = self.position();
+        let mut cursor = self.clone();
+
+        cursor.consume_one_of(&['+', '-']);
+        if !cursor.first().is_some_and(is_digit) {
+            return None;
+        }
+        cursor.consume_while(is_digit);
+        let mut kind = NumberKind::Int;
+
+        if cursor.first() == Some('.') && cursor.second().is_some_and(is_digit) {
+            cursor.consume();
+            cursor.consume_while(is_digit);
+            kind = NumberKind::Float;
+        }
+
+        let mut exponent = cursor.clone();
+        if exponent.consume_one_of(&['e', 'E']).is_some() {
+            exponent.consume_one_of(&['+', '-']);
+            if exponent.first().is_some_and(is_digit) {
+                exponent.consume_while(is_digit);
+                cursor = exponent;
+                kind = NumberKind::Float;
+            }
+        }
+
+        *self = cursor;
+        self.check_invariants();
+        Some(Span::new(kind, self.slice(start..self.position())))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Lines<'i> {
+    cursor: Cursor<'i>,
+    line_number: usize,
+    inclusive: bool,
+}
+
+impl<'i> Lines<'i> {
+    fn new(cursor: Cursor<'i>, inclusive: bool) -> Self {
+        Self {
+            cursor,
+            line_number: 0,
+            inclusive,
+        }
+    }
+}
+
+impl<'i> Iterator for Lines<'i> {
+    type Item = (usize, Span<'i, ()>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor.is_empty() {
+            return None;
+        }
+
+        let mut line = self.cursor.focus_configured_line();
+        if !self.inclusive {
+            line = line.focus(self.cursor.config.newline.strip(line.as_str()).chars());
+        }
+
+        let line_number = self.line_number;
+        self.line_number += 1;
+        Some((line_number, Span::new((), line)))
+    }
+}
+
+/// The input ended before every opening delimiter was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnbalancedError {
+    /// Position of the outermost unclosed opener, or of the cursor if it
+    /// wasn't at an opener at all.
+    pub open_position: usize,
+    /// Number of openers still unclosed at the end of input, 0 when there was
+    /// no opener.
+    pub depth: usize,
+}
+
+/// Indentation mixed tabs and spaces under `strict_indent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentError {
+    /// Position of the first char of the other kind.
+    pub position: usize,
+}
+
+/// The input didn't continue as expected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorError<'i> {
+    pub position: usize,
+    pub expected: String,
+    /// Preview of the input found at `position`.
+    pub found: &'i str,
+}
+
+impl fmt::Display for CursorError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected `{}` at {}, found `{}`",
+            self.expected, self.position, self.found
+        )
+    }
+}
+
+impl std::error::Error for CursorError<'_> {}
+
+#[derive(Debug, Clone)]
+pub struct Span<'i, Kind: 'i> {
+    pub kind: Kind,
+    pub cursor: Cursor<'i>,
+}
+
+impl<'i, Kind: 'i> Span<'i, Kind> {
+    pub fn new(kind: Kind, cursor: Cursor<'i>) -> Self {
+        Self { kind, cursor }
+    }
+
+    pub fn to_kind<OtherKind: 'i>(&self, other_kind: OtherKind) -> Span<'i, OtherKind> {
+        Span::new(other_kind, self.cursor.clone())
+    }
+
+    pub fn map_kind<OtherKind: 'i>(self, f: impl FnOnce(Kind) -> OtherKind) -> Span<'i, OtherKind> {
+        Span::new(f(self.kind), self.cursor)
+    }
+
+    /// Like `map_kind`, but fallible, and `f` can look at the span's text.
+    pub fn try_map_kind<OtherKind: 'i, E>(
+        self,
+        f: impl FnOnce(Kind, &Cursor<'i>) -> Result<OtherKind, E>,
+    ) -> Result<Span<'i, OtherKind>, E> {
+        let kind = f(self.kind, &self.cursor)?;
+        Ok(Span::new(kind, self.cursor))
+    }
+
+    /// Bytes of the focused region, same as through `Deref` to the cursor.
+    pub fn as_bytes(&self) -> &'i [u8] {
+        self.cursor.as_bytes()
+    }
+
+    pub fn kind(&self) -> &Kind {
+        &self.kind
+    }
+
+    pub fn kind_mut(&mut self) -> &mut Kind {
+        &mut self.kind
+    }
+
+    pub fn with_cursor(&self, cursor: Cursor<'i>) -> Self
+    where
+        Kind: Clone,
+    {
+        Span::new(self.kind.clone(), cursor)
+    }
+
+    /// Smallest span covering both spans and anything between them, of this
+    /// span's kind. Both must be over the same input, which is only checked
+    /// in debug builds.
+    pub fn merge(&self, other: &Self) -> Self
+    where
+        Kind: Clone,
+    {
+        let start = if other.position() < self.position() {
+            &other.cursor
+        } else {
+            &self.cursor
+        };
+        let (self_end, other_end) = (self.cursor.end(), other.cursor.end());
+        let end = if other_end.position() > self_end.position() {
+            &other_end
+        } else {
+            &self_end
+        };
+        self.with_cursor(self.cursor.slice(Cursor::range_between(start, end)))
+    }
+
+    /// Splits the span into the text before byte `mid` of it and the rest,
+    /// both of the same kind. Panics if `mid` is past the end of the span or
+    /// not on a char boundary.
+    pub fn split_at(&self, mid: usize) -> (Self, Self)
+    where
+        Kind: Clone,
+    {
+        let mid = self.cursor.focus(self.as_str()[mid..].chars());
+        let head = Cursor::range_between(&self.cursor, &mid);
+        let tail = Cursor::range_between(&mid, &self.cursor.end());
+        (
+            self.with_cursor(self.cursor.slice(head)),
+            self.with_cursor(self.cursor.slice(tail)),
+        )
+    }
+
+    /// Span over the text with surrounding whitespace removed, of the same
+    /// kind.
+    pub fn trim(&self) -> Self
+    where
+        Kind: Clone,
+    {
+        self.with_cursor(self.cursor.trimmed())
+    }
+
+    pub fn trim_start(&self) -> Self
+    where
+        Kind: Clone,
+    {
+        self.with_cursor(self.cursor.trimmed_start())
+    }
+
+    pub fn trim_end(&self) -> Self
+    where
+        Kind: Clone,
+    {
+        self.with_cursor(self.cursor.trimmed_end())
+    }
+
+    /// The lines of `input` this span touches plus up to `before` lines
+    /// before and `after` lines after them, without the last line's ending.
+    /// Lines end as `line_containing` ends them, and a line ending that ends
+    /// the span doesn't pull in the next line.
+    pub fn context_lines(&self, before: usize, after: usize) -> &'i str {
+        let input = self.input();
+        let newline = self.config().newline;
+        let terminator = newline.terminator();
+        let last = self.position() + newline.strip(self.as_str()).len();
+
+        let line_start = |pos: usize| {
+            input[..pos]
+                .rfind(terminator)
+                .map_or(0, |found| found + terminator.len())
+        };
+        let line_end = |pos: usize| {
+            let start = line_start(pos);
+            input[start..]
+                .find(terminator)
+                .map_or(input.len(), |found| start + found + terminator.len())
+        };
+
+        let mut start = line_start(self.position());
+        for _ in 0..before {
+            if start == 0 {
+                break;
+            }
+            start = line_start(start - 1);
+        }
+
+        let mut end = line_end(last);
+        for _ in 0..after {
+            if end == input.len() {
+                break;
+            }
+            end = line_end(end);
+        }
+
+        newline.strip(&input[start..end])
+    }
+
+    /// Byte range of this span within `outer.input()`.
+    ///
+    /// Focusing never changes `input`, so this only differs from the span's
+    /// own position when the span was lexed from a cursor created over a
+    /// slice of `outer`'s input, e.g. `Cursor::from(&document[start..end])`.
+    /// Panics if the span's input doesn't lie within `outer.input()`.
+    pub fn rebase(&self, outer: &Cursor<'i>) -> Range<usize> {
+        let offset = self
+            .cursor
+            .input_offset_in(outer)
+            .expect("span input is not within the outer input");
+        let start = offset + self.position();
+        start..start + self.as_str().len()
+    }
+
+    /// Splits the span at every `sep`, consecutive separators yielding empty
+    /// spans.
+    pub fn split(&self, sep: char) -> impl Iterator<Item = Span<'i, ()>> + 'i {
+        let cursor = self.cursor.clone();
+        self.as_str()
+            .split(sep)
+            .map(move |segment| Span::new((), cursor.focus(segment.chars())))
+    }
+
+    /// Splits off the first line, without its line ending, from the rest of
+    /// the span after it. The rest is empty for a single line.
+    pub fn split_first_line(&self) -> (Span<'i, ()>, Span<'i, ()>) {
+        let mut rest = self.cursor.clone();
+        let line = rest.focus_configured_line();
+        let text = self.config.newline.strip(line.as_str());
+        (Span::new((), line.focus(text.chars())), Span::new((), rest))
+    }
+}
+
+pub trait HasKind {
+    type Kind;
+
+    fn kind(&self) -> &Self::Kind;
+}
+
+impl<'i, Kind: 'i> HasKind for Span<'i, Kind> {
+    type Kind = Kind;
+
+    fn kind(&self) -> &Kind {
+        &self.kind
+    }
+}
+
+impl<'i, Kind: 'i> Deref for Span<'i, Kind> {
+    type Target = Cursor<'i>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cursor
+    }
+}
+
+impl<'i, Kind: 'i> DerefMut for Span<'i, Kind> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cursor
+    }
+}
+
+/// Spans are equal when their kinds and focused text are, wherever in
+/// whichever input they are.
+impl<'i, Kind: PartialEq + 'i> PartialEq for Span<'i, Kind> {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.as_str() == other.as_str()
+    }
+}
+
+impl<'i, Kind: Eq + 'i> Eq for Span<'i, Kind> {}
+
+/// Like equality, hashing only looks at the kind and text, not positions.
+impl<'i, Kind: Hash + 'i> Hash for Span<'i, Kind> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+        self.as_str().hash(state);
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum UniErrorKind {}
@@ -223,3 +1671,1471 @@ pub enum UniErrorKind {}
 pub type UniError<'i> = Span<'i, UniErrorKind>;
 
 pub type UniResult<'i, T> = Result<T, UniError<'i>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peek_byte() {
+        let mut cursor = Cursor::from("aы🦀");
+        assert_eq!(cursor.as_bytes(), "aы🦀".as_bytes());
+        assert_eq!(cursor.peek_byte(), Some(b'a'));
+
+        cursor.consume();
+        assert_eq!(cursor.peek_byte(), Some("ы".as_bytes()[0]));
+
+        cursor.consume();
+        assert_eq!(cursor.peek_byte(), Some("🦀".as_bytes()[0]));
+        assert_eq!(cursor.as_bytes().len(), 4);
+
+        cursor.consume();
+        assert_eq!(cursor.peek_byte(), None);
+        assert!(cursor.as_bytes().is_empty());
+    }
+
+    #[test]
+    fn test_focus_until_str() {
+        let mut cursor = Cursor::from("key -> value");
+        let focused = cursor.focus_until_str(" -> ");
+        assert_eq!(focused.as_str(), "key");
+        assert_eq!(focused.position(), 0);
+        assert_eq!(cursor.as_str(), " -> value");
+
+        let focused = cursor.focus_until_str(";");
+        assert_eq!(focused.as_str(), " -> value");
+        assert_eq!(focused.position(), 3);
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn test_lines() {
+        fn check(input: &str, inclusive: bool, expected: &[(usize, &str, usize)]) {
+            let cursor = Cursor::from(input);
+            let lines = if inclusive {
+                cursor.lines_inclusive()
+            } else {
+                cursor.lines()
+            };
+            let actual: Vec<_> = lines
+                .map(|(number, span)| (number, span.as_str(), span.position()))
+                .collect();
+            assert_eq!(actual, expected);
+        }
+
+        check(
+            "a\nbb\nccc\n",
+            false,
+            &[(0, "a", 0), (1, "bb", 2), (2, "ccc", 5)],
+        );
+        check(
+            "a\nbb\nccc",
+            false,
+            &[(0, "a", 0), (1, "bb", 2), (2, "ccc", 5)],
+        );
+        check(
+            "a\nbb\nccc\n",
+            true,
+            &[(0, "a\n", 0), (1, "bb\n", 2), (2, "ccc\n", 5)],
+        );
+        check(
+            "a\nbb\nccc",
+            true,
+            &[(0, "a\n", 0), (1, "bb\n", 2), (2, "ccc", 5)],
+        );
+        check("", false, &[]);
+    }
+
+    #[test]
+    fn test_consume_crlf() {
+        let mut cursor = Cursor::from("\n\r\nx\r");
+        assert!(cursor.consume_crlf());
+        assert_eq!(cursor.position(), 1);
+        assert!(cursor.consume_crlf());
+        assert_eq!(cursor.position(), 3);
+        assert!(!cursor.consume_crlf());
+        cursor.consume();
+        assert!(!cursor.consume_crlf());
+        assert_eq!(cursor.as_str(), "\r");
+
+        let lines: Vec<_> = Cursor::from("a\r\nb\nc\r\n")
+            .lines()
+            .map(|(_, span)| span.as_str())
+            .collect();
+        assert_eq!(lines, ["a", "b", "c"]);
+
+        let lines: Vec<_> = Cursor::from("a\r\nb\n")
+            .lines_inclusive()
+            .map(|(_, span)| span.as_str())
+            .collect();
+        assert_eq!(lines, ["a\r\n", "b\n"]);
+    }
+
+    #[test]
+    fn test_span_from() {
+        let mut cursor = Cursor::from("let x = 1;");
+        cursor.consume_while(|ch| ch.is_alphabetic());
+        cursor.consume_while(char::is_whitespace);
+
+        let start = cursor.clone();
+        cursor.consume_while(|ch| ch.is_alphabetic());
+        cursor.consume_while(char::is_whitespace);
+        cursor.consume();
+        cursor.consume_while(char::is_whitespace);
+        cursor.consume_while(|ch| ch.is_ascii_digit());
+
+        let range = cursor.span_from(&start);
+        assert_eq!(range, 4..9);
+        assert_eq!(&cursor.input()[range], "x = 1");
+    }
+
+    #[test]
+    fn test_trimmed() {
+        let mut cursor = Cursor::from("first\n  second line \n");
+        cursor.consume_line();
+        let line = cursor.focus_line();
+        assert_eq!(line.position(), 6);
+
+        let trimmed = line.trimmed();
+        assert_eq!(trimmed.as_str(), "second line");
+        assert_eq!(trimmed.position(), 8);
+        assert_eq!(trimmed.input(), line.input());
+
+        let trimmed = line.trimmed_start();
+        assert_eq!(trimmed.as_str(), "second line \n");
+        assert_eq!(trimmed.position(), 8);
+
+        let trimmed = line.trimmed_end();
+        assert_eq!(trimmed.as_str(), "  second line");
+        assert_eq!(trimmed.position(), 6);
+    }
+
+    #[test]
+    fn test_consume_balanced_str() {
+        let mut cursor = Cursor::from("{{ {{x}} }} rest");
+        let inner = cursor.consume_balanced_str("{{", "}}").unwrap();
+        assert_eq!(inner.as_str(), " {{x}} ");
+        assert_eq!(inner.position(), 2);
+        assert_eq!(cursor.as_str(), " rest");
+
+        let mut cursor = Cursor::from("{{ {{x}} ");
+        assert!(cursor.consume_balanced_str("{{", "}}").is_none());
+        assert_eq!(cursor.position(), 0);
+
+        let mut cursor = Cursor::from("x {{}}");
+        assert!(cursor.consume_balanced_str("{{", "}}").is_none());
+        assert_eq!(cursor.position(), 0);
+
+        let mut cursor = Cursor::from("''a''b''");
+        let inner = cursor.consume_balanced_str("''", "''").unwrap();
+        assert_eq!(inner.as_str(), "a");
+        assert_eq!(cursor.as_str(), "b''");
+    }
+
+    #[test]
+    fn test_span_with_cursor() {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Word;
+
+        let mut cursor = Cursor::from(" word ");
+        let span = Span::new(Word, cursor.focus_line());
+        let trimmed = span.with_cursor(span.trimmed());
+        assert_eq!(trimmed.kind, Word);
+        assert_eq!(trimmed.as_str(), "word");
+        assert_eq!(trimmed.position(), 1);
+    }
+
+    #[test]
+    fn test_consumed_line_count() {
+        let mut cursor = Cursor::from("one\ntwo\nthree");
+        assert_eq!(cursor.consumed_line_count(), 0);
+        cursor.consume_while(|ch| ch != '\n');
+        assert_eq!(cursor.consumed_line_count(), 0);
+        cursor.consume_line();
+        assert_eq!(cursor.consumed_line_count(), 1);
+        cursor.consume_line().consume();
+        assert_eq!(cursor.consumed_line_count(), 2);
+        assert_eq!(cursor.as_str(), "hree");
+    }
+
+    #[test]
+    fn test_focus_while_indexed() {
+        fn is_ident(index: usize, ch: char) -> bool {
+            match index {
+                0 => ch == '_' || ch.is_alphabetic(),
+                _ => ch == '_' || ch.is_alphanumeric(),
+            }
+        }
+
+        let mut cursor = Cursor::from("_foo1 bar");
+        assert_eq!(cursor.focus_while_indexed(is_ident).as_str(), "_foo1");
+        assert_eq!(cursor.as_str(), " bar");
+
+        let mut cursor = Cursor::from("1foo");
+        assert!(cursor.focus_while_indexed(is_ident).is_empty());
+        assert_eq!(cursor.position(), 0);
+    }
+
+    #[test]
+    fn test_char_indices() {
+        let mut cursor = Cursor::from("aы🦀b");
+        cursor.consume();
+        let indices: Vec<_> = cursor.char_indices().collect();
+        assert_eq!(indices[0].0, cursor.position());
+        assert_eq!(indices, [(1, 'ы'), (3, '🦀'), (7, 'b')]);
+    }
+
+    #[test]
+    fn test_rest_after() {
+        let cursor = Cursor::from("aы🦀b");
+        assert_eq!(cursor.rest_after(0), "aы🦀b");
+        assert_eq!(cursor.rest_after(2), "🦀b");
+        assert_eq!(cursor.rest_after(3), "b");
+        assert_eq!(cursor.rest_after(4), "");
+        assert_eq!(cursor.rest_after(100), "");
+        assert_eq!(cursor.position(), 0);
+    }
+
+    #[test]
+    fn test_match_positions() {
+        let cursor = Cursor::from("aaa");
+        assert_eq!(cursor.match_positions("aa").collect::<Vec<_>>(), [0]);
+
+        let mut cursor = Cursor::from("a, b, c");
+        cursor.consume();
+        assert_eq!(cursor.match_positions(", ").collect::<Vec<_>>(), [1, 4]);
+        assert_eq!(cursor.match_positions(";").count(), 0);
+    }
+
+    #[test]
+    fn test_span_kind() {
+        fn kinds<T: HasKind>(items: &[T]) -> Vec<&T::Kind> {
+            items.iter().map(HasKind::kind).collect()
+        }
+
+        let mut cursor = Cursor::from("ab");
+        let mut spans = vec![
+            Span::new(1, cursor.focus_char()),
+            Span::new(2, cursor.focus_char()),
+        ];
+        *spans[1].kind_mut() += 1;
+        assert_eq!(*spans[0].kind(), 1);
+        assert_eq!(kinds(&spans), [&1, &3]);
+    }
+
+    #[test]
+    fn test_consume_balanced() {
+        let mut cursor = Cursor::from("f(a, (b), c) + 1");
+        cursor.consume();
+        let inner = cursor.consume_balanced('(', ')').unwrap();
+        assert_eq!(inner.as_str(), "a, (b), c");
+        assert_eq!(inner.position(), 2);
+        assert_eq!(cursor.as_str(), " + 1");
+
+        let mut cursor = Cursor::from("x = ((a)");
+        cursor.consume_until(|ch| ch == '=');
+        assert_eq!(
+            cursor.consume_balanced('(', ')').unwrap_err(),
+            UnbalancedError {
+                open_position: 3,
+                depth: 0,
+            }
+        );
+        cursor.consume();
+        assert_eq!(
+            cursor.consume_balanced('(', ')').unwrap_err(),
+            UnbalancedError {
+                open_position: 4,
+                depth: 1,
+            }
+        );
+        assert_eq!(cursor.position(), 4);
+
+        let mut cursor = Cursor::from("(((");
+        assert_eq!(
+            cursor.consume_balanced('(', ')').unwrap_err(),
+            UnbalancedError {
+                open_position: 0,
+                depth: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_skip_bom() {
+        let mut cursor = Cursor::from("\u{FEFF}\u{FEFF}key");
+        assert!(cursor.skip_bom());
+        assert_eq!(cursor.position(), 3);
+        assert!(!cursor.skip_bom());
+        assert_eq!(cursor.position(), 3);
+
+        let mut cursor = Cursor::from("key");
+        assert!(!cursor.skip_bom());
+        assert_eq!(cursor.position(), 0);
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "debug-invariants"))]
+    #[should_panic]
+    fn test_focus_foreign_chars() {
+        let first = String::from("first");
+        let second = String::from("second");
+        Cursor::from(first.as_str()).focus(second.chars());
+    }
+
+    // The bounds checks after each mutation only run with
+    // `cargo test --features debug-invariants`, in release builds too.
+    #[test]
+    fn test_invariants_hold_on_random_inputs() {
+        const ALPHABET: [char; 8] = ['a', ' ', '\n', '(', ')', 'ы', '🦀', '\r'];
+
+        let mut seed: u32 = 0x2545_F491;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as usize
+        };
+
+        for _ in 0..200 {
+            let len = next() % 32;
+            let input: String = (0..len)
+                .map(|_| ALPHABET[next() % ALPHABET.len()])
+                .collect();
+            let mut cursor = Cursor::from(input.as_str());
+
+            while !cursor.is_empty() {
+                let before = cursor.position();
+                match next() % 6 {
+                    0 => {
+                        cursor.focus_line();
+                    }
+                    1 => {
+                        cursor.focus_until_str(")");
+                        cursor.consume();
+                    }
+                    2 => {
+                        let _ = cursor.consume_balanced('(', ')');
+                        cursor.consume();
+                    }
+                    3 => {
+                        cursor.consume_crlf();
+                        cursor.consume();
+                    }
+                    4 => {
+                        cursor.focus_while(char::is_alphabetic).trimmed();
+                        cursor.consume();
+                    }
+                    _ => {
+                        cursor.consume_while(|ch| ch != '\n').consume();
+                    }
+                }
+                assert!(cursor.position() > before);
+                assert!(cursor.position() <= input.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_consume_one_of() {
+        let mut cursor = Cursor::from("+-*");
+        assert_eq!(cursor.consume_one_of(&['+', '-']), Some('+'));
+        assert_eq!(cursor.consume_one_of(&['+', '-']), Some('-'));
+        assert_eq!(cursor.consume_one_of(&['+', '-']), None);
+        assert_eq!(cursor.position(), 2);
+        assert_eq!(cursor.consume_one_of_str("/*"), Some('*'));
+        assert_eq!(cursor.consume_one_of_str("/*"), None);
+    }
+
+    #[test]
+    fn test_span_split() {
+        let mut cursor = Cursor::from("x\na,,b\n");
+        cursor.consume_line();
+        let line = Span::new((), cursor.focus_line().trimmed_end());
+        let fields: Vec<_> = line
+            .split(',')
+            .map(|span| (span.as_str(), span.position()))
+            .collect();
+        assert_eq!(fields, [("a", 2), ("", 4), ("b", 5)]);
+    }
+
+    #[test]
+    fn test_span_merge_and_split_at() {
+        let mut cursor = Cursor::from("let x = 1;");
+        cursor.focus_until_str("x");
+        let name = Span::new(1, cursor.focus_while(char::is_alphabetic));
+        cursor.focus_until_str("1");
+        let value = Span::new(2, cursor.focus_while(|ch| ch.is_ascii_digit()));
+
+        let merged = value.merge(&name);
+        assert_eq!(merged.as_str(), "x = 1");
+        assert_eq!(merged.kind, 2);
+        assert_eq!(name.merge(&name).as_str(), "x");
+
+        let (head, tail) = merged.split_at(2);
+        assert_eq!((head.as_str(), tail.as_str()), ("x ", "= 1"));
+        assert_eq!((head.position(), tail.position()), (4, 6));
+        assert_eq!(head.merge(&tail).as_str(), "x = 1");
+
+        let (head, tail) = merged.split_at(5);
+        assert_eq!((head.as_str(), tail.as_str()), ("x = 1", ""));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "cursors over different inputs")]
+    fn test_span_merge_different_inputs() {
+        let first = String::from("first");
+        let second = String::from("second");
+        let first = Span::new((), Cursor::from(first.as_str()));
+        let second = Span::new((), Cursor::from(second.as_str()));
+        first.merge(&second);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "cursors over different inputs")]
+    fn test_span_from_different_inputs() {
+        let first = String::from("first");
+        let second = String::from("second");
+        let start = Cursor::from(first.as_str());
+        let end = Cursor::from(second.as_str());
+        end.span_from(&start);
+    }
+
+    #[test]
+    fn test_byte_offset_of_char() {
+        let cursor = Cursor::from("aы🦀b");
+        assert_eq!(cursor.byte_offset_of_char(0), Some(0));
+        assert_eq!(cursor.byte_offset_of_char(1), Some(1));
+        assert_eq!(cursor.byte_offset_of_char(2), Some(3));
+        assert_eq!(cursor.byte_offset_of_char(3), Some(7));
+        assert_eq!(cursor.byte_offset_of_char(4), None);
+        assert_eq!(cursor.byte_offset_of_char(5), None);
+        assert_eq!(Cursor::from("").byte_offset_of_char(0), None);
+    }
+
+    #[test]
+    fn test_peek_while() {
+        let cursor = Cursor::from("123abc");
+        assert_eq!(cursor.peek_while(|ch| ch.is_ascii_digit()), "123");
+        assert_eq!(cursor.peek_while(char::is_alphabetic), "");
+        assert_eq!(cursor.position(), 0);
+        assert_eq!(cursor.as_str(), "123abc");
+    }
+
+    #[test]
+    fn test_cursor_iterator() {
+        let mut cursor = Cursor::from("abcdefg");
+        assert_eq!(cursor.drain().take(3).collect::<String>(), "abc");
+        assert_eq!(cursor.as_str(), "defg");
+        assert_eq!(cursor.position(), 3);
+
+        for ch in &mut cursor {
+            if ch == 'e' {
+                break;
+            }
+        }
+        assert_eq!(cursor.as_str(), "fg");
+        assert_eq!(cursor.into_iter().collect::<String>(), "fg");
+    }
+
+    #[test]
+    #[should_panic(expected = "span input is not within the outer input")]
+    fn test_span_rebase_unrelated() {
+        let document = String::from("document");
+        let other = String::from("other");
+        let span = Span::new((), Cursor::from(other.as_str()));
+        span.rebase(&Cursor::from(document.as_str()));
+    }
+
+    #[test]
+    fn test_span_rebase() {
+        let document = "key = [a, bb]";
+        let outer = Cursor::from(document);
+
+        let mut region = Cursor::from(&document[6..]);
+        region.consume();
+        region.consume_until(|ch| ch == ' ');
+        let span = Span::new((), region.focus_while(|ch| ch != ']'));
+        assert_eq!(span.position(), 4);
+        assert_eq!(span.rebase(&outer), 10..12);
+        assert_eq!(&document[span.rebase(&outer)], "bb");
+
+        let mut cursor = outer.clone();
+        let span = Span::new((), cursor.focus_until_str(" "));
+        assert_eq!(span.rebase(&outer), 0..3);
+    }
+
+    #[test]
+    fn test_consume_until_any() {
+        let mut cursor = Cursor::from("a,b;c");
+        assert_eq!(cursor.consume_until_any(&[',', ';']), Some(','));
+        assert_eq!(cursor.as_str(), "b;c");
+        assert_eq!(cursor.focus_until_any(&[',', ';']).as_str(), "b");
+        assert_eq!(cursor.as_str(), ";c");
+        assert!(cursor.focus_until_any(&[',', ';']).is_empty());
+        assert_eq!(cursor.consume_until_any(&[',', ';']), Some(';'));
+        assert_eq!(cursor.focus_until_any(&[',', ';']).as_str(), "c");
+        assert!(cursor.is_empty());
+        assert_eq!(cursor.consume_until_any(&[',', ';']), None);
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn test_utf16_position() {
+        let mut cursor = Cursor::from("a🦀b\n😀c");
+        assert_eq!(cursor.utf16_position(), 0);
+        cursor.consume();
+        cursor.consume();
+        assert_eq!(cursor.utf16_position(), 3);
+        assert_eq!(cursor.utf16_line_col(), (0, 3));
+        cursor.consume_line();
+        cursor.consume();
+        assert_eq!(cursor.utf16_position(), 7);
+        assert_eq!(cursor.utf16_line_col(), (1, 2));
+    }
+
+    #[test]
+    fn test_expect_str() {
+        let mut cursor = Cursor::from("let x\n = 1");
+        assert_eq!(cursor.expect_str("let"), Ok(()));
+        assert_eq!(cursor.position(), 3);
+
+        let error = cursor.expect_str(" var").unwrap_err();
+        assert_eq!(
+            error,
+            CursorError {
+                position: 3,
+                expected: " var".to_string(),
+                found: " x",
+            }
+        );
+        assert_eq!(error.to_string(), "expected ` var` at 3, found ` x`");
+        assert_eq!(cursor.position(), 3);
+
+        assert!(cursor.consume_str(" x"));
+        assert!(!cursor.consume_str("="));
+        assert_eq!(cursor.expect_str("").map_err(|e| e.found), Ok(()));
+    }
+
+    #[test]
+    fn test_dedent() {
+        let mut cursor = Cursor::from("key: |\n    a\n  b\n\n\t    c\n   \r\n");
+        cursor.consume_line();
+        assert_eq!(cursor.dedent(4), "a\nb\n\n c\n\r\n");
+        assert_eq!(cursor.dedent(0), cursor.as_str());
+    }
+
+    #[test]
+    fn test_focus_balanced() {
+        let mut inner_cursor = Cursor::from("[a[b]]c");
+        let mut outer_cursor = inner_cursor.clone();
+        let inner = inner_cursor.focus_balanced_inner('[', ']').unwrap();
+        let outer = outer_cursor.focus_balanced_outer('[', ']').unwrap();
+
+        assert_eq!(inner.as_str(), "a[b]");
+        assert_eq!(outer.as_str(), "[a[b]]");
+        assert_eq!(inner.position(), outer.position() + '['.len_utf8());
+        assert_eq!(
+            inner.as_str().len(),
+            outer.as_str().len() - '['.len_utf8() - ']'.len_utf8()
+        );
+        assert_eq!(inner_cursor.as_str(), "c");
+        assert_eq!(outer_cursor.as_str(), "c");
+
+        let mut cursor = Cursor::from("[a");
+        assert!(cursor.focus_balanced_outer('[', ']').is_err());
+        assert_eq!(cursor.position(), 0);
+    }
+
+    #[test]
+    fn test_line_col() {
+        let mut cursor = Cursor::from("ab\nы🦀c\n");
+        assert_eq!(cursor.line_col(), (0, 0));
+        cursor.consume();
+        assert_eq!(cursor.line_col(), (0, 1));
+        cursor.consume_line();
+        assert_eq!(cursor.line_col(), (1, 0));
+        cursor.consume();
+        cursor.consume();
+        assert_eq!(cursor.line_col(), (1, 2));
+        cursor.consume_line();
+        assert_eq!(cursor.line_col(), (2, 0));
+    }
+
+    #[test]
+    fn test_consume_escaped_until() {
+        let mut cursor = Cursor::from(r#"a\"b" c"#);
+        let content = cursor.consume_escaped_until('"', '\\');
+        assert_eq!(content.as_str(), r#"a\"b"#);
+        assert_eq!(cursor.as_str(), " c");
+
+        let mut cursor = Cursor::from(r#"ab\"#);
+        assert_eq!(cursor.consume_escaped_until('"', '\\').as_str(), r#"ab\"#);
+        assert!(cursor.is_empty());
+
+        let mut cursor = Cursor::from(r#"\\"x"#);
+        assert_eq!(cursor.consume_escaped_until('"', '\\').as_str(), r#"\\"#);
+        assert_eq!(cursor.as_str(), "x");
+    }
+
+    #[test]
+    fn test_cursor_default() {
+        let mut cursor = Cursor::from("abc");
+        cursor.consume();
+        let taken = std::mem::take(&mut cursor);
+        assert_eq!(taken.as_str(), "bc");
+        assert!(cursor.is_empty());
+        assert_eq!(cursor.position(), 0);
+    }
+
+    #[test]
+    fn test_slice() {
+        let mut cursor = Cursor::from("aы🦀b");
+        cursor.consume_line();
+        let sliced = cursor.slice(1..7);
+        assert_eq!(sliced.as_str(), "ы🦀");
+        assert_eq!(sliced.position(), 1);
+        assert_eq!(sliced.position() + sliced.as_str().len(), 7);
+        assert!(cursor.slice(8..8).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_slice_not_char_boundary() {
+        Cursor::from("aы").slice(0..2);
+    }
+
+    #[test]
+    #[should_panic]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn test_slice_inverted() {
+        Cursor::from("abc").slice(2..1);
+    }
+
+    #[test]
+    fn test_focus_number() {
+        fn check(input: &str, expected: Option<(NumberKind, &str)>, rest: &str) {
+            let mut cursor = Cursor::from(input);
+            let number = cursor.focus_number();
+            assert_eq!(number.map(|span| (span.kind, span.as_str())), expected);
+            assert_eq!(cursor.as_str(), rest);
+        }
+
+        check("42;", Some((NumberKind::Int, "42")), ";");
+        check("-3.25 ", Some((NumberKind::Float, "-3.25")), " ");
+        check("1e+5", Some((NumberKind::Float, "1e+5")), "");
+        check("2.5E-3x", Some((NumberKind::Float, "2.5E-3")), "x");
+        check("1.", Some((NumberKind::Int, "1")), ".");
+        check("1..2", Some((NumberKind::Int, "1")), "..2");
+        check("1e", Some((NumberKind::Int, "1")), "e");
+        check("1e+", Some((NumberKind::Int, "1")), "e+");
+        check(".5", None, ".5");
+        check("+", None, "+");
+        check("", None, "");
+    }
+
+    #[test]
+    fn test_focus_identifier() {
+        fn check(input: &str, expected: Option<&str>) {
+            let mut cursor = Cursor::from(input);
+            let identifier = cursor.focus_identifier();
+            assert_eq!(identifier.as_ref().map(|span| span.as_str()), expected);
+        }
+
+        check("_foo bar", Some("_foo"));
+        check("x1_y2+", Some("x1_y2"));
+        check("1foo", None);
+        check("", None);
+
+        #[cfg(feature = "unicode-ident")]
+        check("café = 1", Some("café"));
+        #[cfg(not(feature = "unicode-ident"))]
+        check("café = 1", Some("caf"));
+    }
+
+    #[test]
+    fn test_take() {
+        let mut cursor = Cursor::from("aы🦀 b;c");
+        let span = cursor.take(3);
+        assert_eq!(span.as_str(), "aы🦀");
+        assert_eq!(cursor.position(), 7);
+
+        let span = cursor.take_while(char::is_whitespace);
+        assert_eq!(span.as_str(), " ");
+        assert_eq!(cursor.position(), 8);
+
+        let span = cursor.take_until(|ch| ch == ';');
+        assert_eq!(span.as_str(), "b;");
+        assert_eq!(cursor.as_str(), "c");
+
+        assert_eq!(cursor.take(5).as_str(), "c");
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn test_same_input() {
+        let input = String::from("same text");
+        let copy = input.clone();
+
+        let mut cursor = Cursor::from(input.as_str());
+        let focused = cursor.focus_n(4);
+        assert!(cursor.same_input(&focused));
+        assert!(!cursor.same_input(&Cursor::from(copy.as_str())));
+        assert!(!cursor.same_input(&Cursor::from(&input[..4])));
+    }
+
+    #[test]
+    fn test_consume_comment() {
+        let mut cursor = Cursor::from("// line\nnext");
+        assert_eq!(cursor.consume_comment(), Some(CommentKind::Line));
+        assert_eq!(cursor.as_str(), "\nnext");
+        assert_eq!(cursor.consume_comment(), None);
+        assert_eq!(cursor.position(), 7);
+
+        let mut cursor = Cursor::from("/* a /* b */ c */ d");
+        assert_eq!(cursor.consume_comment(), Some(CommentKind::Block));
+        assert_eq!(cursor.as_str(), " d");
+
+        let mut cursor = Cursor::from("/* a /* b */ c");
+        assert_eq!(cursor.consume_comment(), Some(CommentKind::Block));
+        assert!(cursor.is_empty());
+
+        let mut cursor = Cursor::from("/ 2");
+        assert_eq!(cursor.consume_comment(), None);
+        assert_eq!(cursor.position(), 0);
+    }
+
+    #[test]
+    fn test_span_try_map_kind() {
+        #[derive(Debug, PartialEq)]
+        struct Num(i64);
+
+        let mut cursor = Cursor::from("42 x");
+        let number = cursor.take_while(|ch| ch.is_ascii_digit());
+        let number = number
+            .try_map_kind(|_, cursor| cursor.as_str().parse::<i64>().map(Num))
+            .unwrap();
+        assert_eq!(number.kind, Num(42));
+        assert_eq!(number.as_str(), "42");
+
+        cursor.consume();
+        let word = cursor.take(1);
+        assert!(word
+            .try_map_kind(|_, cursor| cursor.as_str().parse::<i64>().map(Num))
+            .is_err());
+
+        let mapped = Span::new(1, cursor.clone()).map_kind(|kind| kind + 1);
+        assert_eq!(mapped.kind, 2);
+    }
+
+    #[test]
+    fn test_char_boundary() {
+        let cursor = Cursor::from("a🦀b");
+        assert_eq!(cursor.floor_char_boundary(0), 0);
+        assert_eq!(cursor.floor_char_boundary(1), 1);
+        assert_eq!(cursor.floor_char_boundary(3), 1);
+        assert_eq!(cursor.ceil_char_boundary(3), 5);
+        assert_eq!(cursor.ceil_char_boundary(5), 5);
+        assert_eq!(cursor.floor_char_boundary(100), 6);
+        assert_eq!(cursor.ceil_char_boundary(100), 6);
+    }
+
+    #[test]
+    fn test_fold_while() {
+        let mut cursor = Cursor::from("1234+5");
+        let (sum, digits) = cursor.fold_while(0, |sum, ch| Some(sum + ch.to_digit(10)?));
+        assert_eq!(sum, 10);
+        assert_eq!(digits.as_str(), "1234");
+        assert_eq!(cursor.as_str(), "+5");
+
+        let (sum, digits) = cursor.fold_while(0, |sum, ch| Some(sum + ch.to_digit(10)?));
+        assert_eq!(sum, 0);
+        assert!(digits.is_empty());
+    }
+
+    #[test]
+    fn test_consume_until_unescaped() {
+        let mut cursor = Cursor::from("a\\\"b\\\nc\nd\"");
+        let content = cursor.consume_until_unescaped(&['"', '\n'], '\\');
+        assert_eq!(content.as_str(), "a\\\"b\\\nc");
+        assert_eq!(cursor.first(), Some('\n'));
+
+        cursor.consume();
+        assert_eq!(
+            cursor.consume_until_unescaped(&['"', '\n'], '\\').as_str(),
+            "d"
+        );
+        assert_eq!(cursor.first(), Some('"'));
+
+        let mut cursor = Cursor::from("abc\\");
+        assert_eq!(
+            cursor.consume_until_unescaped(&['"'], '\\').as_str(),
+            "abc\\"
+        );
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn test_span_as_bytes() {
+        let mut cursor = Cursor::from("ы🦀 rest");
+        let span = cursor.take(2);
+        assert_eq!(span.as_bytes(), span.as_str().as_bytes());
+        assert_eq!(span.as_bytes(), "ы🦀".as_bytes());
+    }
+
+    #[test]
+    fn test_consume_while_lookahead() {
+        let mut cursor = Cursor::from("/* a * b */ c");
+        cursor.consume_str("/*");
+        let body = cursor.focus_with(|cursor| {
+            cursor.consume_while_lookahead(|ch, next| !(ch == '*' && next == Some('/')));
+        });
+        assert_eq!(body.as_str(), " a * b ");
+        assert_eq!(cursor.as_str(), "*/ c");
+
+        let mut cursor = Cursor::from("ab");
+        cursor.consume_while_lookahead(|_, next| next.is_some());
+        assert_eq!(cursor.as_str(), "b");
+    }
+
+    #[test]
+    fn test_position_checked() {
+        let mut cursor = Cursor::from("ab\ncd");
+        assert_eq!(cursor.position_checked(), Some(0));
+        cursor.consume_line();
+        assert_eq!(cursor.position_checked(), Some(3));
+        let focused = cursor.focus_n(1);
+        assert_eq!(focused.position_checked(), Some(focused.position()));
+        cursor.consume_n(10);
+        assert_eq!(cursor.position_checked(), Some(5));
+    }
+
+    #[test]
+    fn test_consume_repeated() {
+        let mut cursor = Cursor::from("### Title");
+        assert_eq!(cursor.consume_repeated('#', 1, 6), Some(3));
+        assert_eq!(cursor.as_str(), " Title");
+
+        let mut cursor = Cursor::from("####### Title");
+        assert_eq!(cursor.consume_repeated('#', 1, 6), Some(6));
+        assert_eq!(cursor.as_str(), "# Title");
+
+        let mut cursor = Cursor::from("Title");
+        assert_eq!(cursor.consume_repeated('#', 1, 6), None);
+        assert_eq!(cursor.as_str(), "Title");
+        assert_eq!(cursor.consume_repeated('#', 0, 6), Some(0));
+
+        let mut cursor = Cursor::from("ыыx");
+        assert_eq!(cursor.consume_repeated('ы', 3, 6), None);
+        assert_eq!(cursor.consume_repeated('ы', 2, 6), Some(2));
+        assert_eq!(cursor.as_str(), "x");
+    }
+
+    #[test]
+    fn test_focus_rest() {
+        let mut cursor = Cursor::from("head ы rest");
+        cursor.consume_n(5);
+        let before = cursor.as_str();
+        let rest = cursor.focus_rest();
+        assert_eq!(rest.as_str(), before);
+        assert_eq!(rest.position(), 5);
+        assert!(cursor.is_empty());
+        assert!(cursor.focus_rest().is_empty());
+    }
+
+    #[test]
+    fn test_consume_while_bounded() {
+        let long = "a".repeat(10_000) + "b";
+        let mut cursor = Cursor::from(long.as_str());
+        assert_eq!(
+            cursor.consume_while_bounded(100, |ch| ch == 'a'),
+            (100, true)
+        );
+        assert_eq!(cursor.position(), 100);
+
+        let mut cursor = Cursor::from("aaab");
+        assert_eq!(cursor.consume_while_bounded(3, |ch| ch == 'a'), (3, false));
+        assert_eq!(cursor.as_str(), "b");
+
+        let mut cursor = Cursor::from("aa");
+        assert_eq!(cursor.consume_while_bounded(5, |ch| ch == 'a'), (2, false));
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn test_span_eq() {
+        let mut cursor = Cursor::from("ab ab abc");
+        let first = cursor.take(2);
+        cursor.consume();
+        let second = cursor.take(2);
+        assert_ne!(first.position(), second.position());
+        assert_eq!(first, second);
+        assert_eq!(first, Span::new((), Cursor::from("ab")));
+
+        cursor.consume();
+        assert_ne!(first, cursor.take(3));
+        assert_ne!(first.to_kind(1), first.to_kind(2));
+    }
+
+    #[test]
+    fn test_focus_block_until() {
+        let fenced = "```rust\nlet a = 1;\n\nlet b = 2;\n```\nafter";
+        let is_fence = |line: &str| line.starts_with("```");
+
+        let mut cursor = Cursor::from(fenced);
+        cursor.consume_line();
+        let block = cursor.focus_block_until(is_fence, false);
+        assert_eq!(block.as_str(), "let a = 1;\n\nlet b = 2;\n");
+        assert_eq!(cursor.as_str(), "```\nafter");
+
+        let mut cursor = Cursor::from(fenced);
+        cursor.consume_line();
+        let block = cursor.focus_block_until(is_fence, true);
+        assert_eq!(block.as_str(), "let a = 1;\n\nlet b = 2;\n```\n");
+        assert_eq!(cursor.as_str(), "after");
+
+        let mut cursor = Cursor::from("no\nfence");
+        assert_eq!(
+            cursor.focus_block_until(is_fence, false).as_str(),
+            "no\nfence"
+        );
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn test_with_limit() {
+        let buffer = "ready|pending";
+        let mut cursor = Cursor::from(buffer).with_limit(5);
+        assert_eq!(cursor.as_str(), "ready");
+        assert_eq!(cursor.input(), buffer);
+
+        cursor.consume_while(|_| true);
+        assert!(cursor.is_empty());
+        assert_eq!(cursor.consume(), None);
+        assert_eq!(cursor.position(), 5);
+
+        let mut cursor = cursor.with_limit(9);
+        assert_eq!(cursor.focus_rest().as_str(), "|pen");
+
+        let cursor = cursor.with_limit(buffer.len());
+        assert_eq!(cursor.as_str(), "ding");
+    }
+
+    #[test]
+    fn test_skip_ws_count_newlines() {
+        let mut cursor = Cursor::from("  \t x");
+        assert_eq!(cursor.skip_ws_count_newlines(), 0);
+        assert_eq!(cursor.as_str(), "x");
+
+        let mut cursor = Cursor::from(" \r\n  x");
+        assert_eq!(cursor.skip_ws_count_newlines(), 1);
+        assert_eq!(cursor.as_str(), "x");
+
+        let mut cursor = Cursor::from("\n  \n\n\t\nx\n");
+        assert_eq!(cursor.skip_ws_count_newlines(), 4);
+        assert_eq!(cursor.as_str(), "x\n");
+
+        assert_eq!(Cursor::from("x").skip_ws_count_newlines(), 0);
+    }
+
+    #[test]
+    fn test_span_split_first_line() {
+        let mut cursor = Cursor::from("> note title\r\nbody\nmore");
+        cursor.consume_n(2);
+        let span = cursor.take_while(|_| true);
+        let (first, rest) = span.split_first_line();
+        assert_eq!(first.as_str(), "note title");
+        assert_eq!(first.position(), 2);
+        assert_eq!(rest.as_str(), "body\nmore");
+        assert_eq!(rest.position(), 14);
+        assert!(first.same_input(&cursor));
+
+        let span = Span::new((), Cursor::from("single"));
+        let (first, rest) = span.split_first_line();
+        assert_eq!(first.as_str(), "single");
+        assert!(rest.is_empty());
+        assert_eq!(rest.position(), 6);
+    }
+
+    #[test]
+    fn test_consume_balanced_escaped() {
+        let mut cursor = Cursor::from(r"(a \( (b) \\) rest");
+        let inner = cursor.consume_balanced_escaped('(', ')', '\\').unwrap();
+        assert_eq!(inner.as_str(), r"a \( (b) \\");
+        assert_eq!(inner.position(), 1);
+        assert_eq!(cursor.as_str(), " rest");
+
+        let mut cursor = Cursor::from(r"(a \)");
+        assert!(cursor.consume_balanced_escaped('(', ')', '\\').is_none());
+        assert_eq!(cursor.position(), 0);
+        assert!(Cursor::from("x()")
+            .consume_balanced_escaped('(', ')', '\\')
+            .is_none());
+    }
+
+    #[test]
+    fn test_cursor_config() {
+        let mut cursor = Cursor::from("\tx\ty");
+        cursor.consume_n(3);
+        assert_eq!(cursor.line_col(), (0, 3));
+
+        let config = CursorConfig {
+            tab_width: 4,
+            ..CursorConfig::default()
+        };
+        let mut cursor = Cursor::with_config("a\n\tx\ty", config);
+        cursor.consume_n(3);
+        assert_eq!(cursor.line_col(), (1, 4));
+        cursor.consume_n(2);
+        assert_eq!(cursor.line_col(), (1, 8));
+        assert_eq!(cursor.focus_char().config(), config);
+
+        let config = CursorConfig {
+            newline: NewlineStyle::CrLf,
+            ..CursorConfig::default()
+        };
+        let mut cursor = Cursor::with_config("a\nb\r\nc", config);
+        let lines: Vec<_> = cursor
+            .clone()
+            .lines()
+            .map(|(_, line)| line.as_str())
+            .collect();
+        assert_eq!(lines, ["a\nb", "c"]);
+        cursor.consume_n(6);
+        assert_eq!(cursor.line_col(), (1, 1));
+
+        let config = CursorConfig {
+            newline: NewlineStyle::Lf,
+            ..CursorConfig::default()
+        };
+        let lines: Vec<_> = Cursor::with_config("a\r\nb", config)
+            .lines()
+            .map(|(_, line)| line.as_str())
+            .collect();
+        assert_eq!(lines, ["a\r", "b"]);
+    }
+
+    #[test]
+    fn test_split_top_level() {
+        let mut cursor = Cursor::from("f(a, g(b, c), d)");
+        cursor.consume_str("f");
+        let args = cursor.consume_balanced('(', ')').unwrap();
+        let segments = args.split_top_level(',', '(', ')');
+        let texts: Vec<_> = segments.iter().map(|span| span.as_str()).collect();
+        assert_eq!(texts, ["a", " g(b, c)", " d"]);
+        assert_eq!(segments[1].position(), 4);
+
+        let texts: Vec<_> = Cursor::from("")
+            .split_top_level(',', '(', ')')
+            .iter()
+            .map(|span| span.as_str())
+            .collect();
+        assert_eq!(texts, [""]);
+        let texts: Vec<_> = Cursor::from("a),b,")
+            .split_top_level(',', '(', ')')
+            .iter()
+            .map(|span| span.as_str())
+            .collect();
+        assert_eq!(texts, ["a)", "b", ""]);
+    }
+
+    #[test]
+    fn test_consume_while_with_cursor() {
+        let mut cursor = Cursor::from("a -> b --> c");
+        let mut dashes = 0;
+        cursor.consume_while_with_cursor(|cursor, ch| {
+            dashes += usize::from(ch == '-');
+            !cursor.as_str().starts_with("-->")
+        });
+        assert_eq!(cursor.as_str(), "--> c");
+        assert_eq!(dashes, 2);
+    }
+
+    #[test]
+    fn test_progressed_since() {
+        let mut cursor = Cursor::from("ab");
+        let before = cursor.position();
+        assert!(!cursor.progressed_since(before));
+        cursor.consume();
+        assert!(cursor.progressed_since(before));
+        cursor.assert_progress(before);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "no progress past position 1")]
+    fn test_assert_progress_stalled() {
+        let mut cursor = Cursor::from("a b");
+        cursor.consume();
+        loop {
+            let before = cursor.position();
+            cursor.consume_while(char::is_alphabetic);
+            cursor.assert_progress(before);
+        }
+    }
+
+    #[test]
+    fn test_focus_until_unbalanced() {
+        let mut cursor = Cursor::from("a + (b * (c)) - d) * e");
+        let inner = cursor.focus_until_unbalanced('(', ')');
+        assert_eq!(inner.as_str(), "a + (b * (c)) - d");
+        assert_eq!(cursor.as_str(), ") * e");
+
+        let mut cursor = Cursor::from("(unclosed");
+        assert_eq!(
+            cursor.focus_until_unbalanced('(', ')').as_str(),
+            "(unclosed"
+        );
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        let text = "\n\nfirst\nstill first\n\n  \n\t\nsecond\r\n\nthird\n   \n";
+        let paragraphs: Vec<_> = Cursor::from(text).paragraphs().collect();
+        let texts: Vec<_> = paragraphs.iter().map(|span| span.as_str()).collect();
+        assert_eq!(texts, ["first\nstill first", "second", "third"]);
+        assert_eq!(paragraphs[0].position(), 2);
+
+        assert_eq!(Cursor::from(" \n\n").paragraphs().count(), 0);
+    }
+
+    #[test]
+    fn test_span_trim() {
+        let mut cursor = Cursor::from("key = \t value \n");
+        cursor.consume_str("key =");
+        let span = cursor.take_while(|_| true).map_kind(|()| 7);
+        let trimmed = span.trim();
+        assert_eq!(trimmed.as_str(), "value");
+        assert_eq!(trimmed.position(), 8);
+        assert_eq!(*trimmed.kind(), 7);
+        assert_eq!(span.trim_start().as_str(), "value \n");
+        assert_eq!(span.trim_end().as_str(), " \t value");
+        assert_eq!(span.trim_end().position(), 5);
+
+        let blank = Span::new((), Cursor::from(" \t\n "));
+        assert!(blank.trim().is_empty());
+        assert!(Span::new((), Cursor::from("")).trim().is_empty());
+    }
+
+    #[test]
+    fn test_consume_while_escaped() {
+        let mut cursor = Cursor::from(r"foo\ bar\\ baz");
+        cursor.consume_while_escaped('\\', |ch| ch != ' ');
+        assert_eq!(cursor.as_str(), " baz");
+
+        let mut cursor = Cursor::from(r"ab\");
+        cursor.consume_while_escaped('\\', char::is_alphabetic);
+        assert!(cursor.is_empty());
+    }
+
+    #[test]
+    fn test_consume_while_ascii() {
+        for input in ["12345x", "123", "", "12ы3", "١٢٣", "42🦀"] {
+            let mut by_char = Cursor::from(input);
+            by_char.consume_while(|ch| ch.is_ascii_digit());
+            let mut by_byte = Cursor::from(input);
+            by_byte.consume_while_ascii(|byte| byte.is_ascii_digit());
+            assert_eq!(by_byte.position(), by_char.position(), "{input:?}");
+        }
+
+        let mut cursor = Cursor::from("abcЖ");
+        cursor.consume_while_ascii(|_| true);
+        assert_eq!(cursor.as_str(), "Ж");
+    }
+
+    #[test]
+    fn test_skip_shebang() {
+        let mut cursor = Cursor::from("#!/usr/bin/env run\nfn main() {}");
+        assert!(cursor.skip_shebang());
+        assert_eq!(cursor.as_str(), "\nfn main() {}");
+        assert!(!cursor.skip_shebang());
+
+        let mut cursor = Cursor::from("\u{FEFF}#!/bin/sh");
+        cursor.skip_bom();
+        assert!(cursor.skip_shebang());
+        assert!(cursor.is_empty());
+
+        for input in [
+            "#![allow(dead_code)]",
+            "#! /* c */\n  [attr]",
+            "fn main() {}",
+            "",
+        ] {
+            let mut cursor = Cursor::from(input);
+            assert!(!cursor.skip_shebang(), "{input:?}");
+            assert_eq!(cursor.position(), 0);
+        }
+    }
+
+    #[test]
+    fn test_consume_prefix() {
+        let input = "let x";
+        let mut cursor = Cursor::from(input);
+        let keyword = cursor.consume_prefix("let").unwrap();
+        assert_eq!(keyword, "let");
+        assert!(std::ptr::eq(keyword.as_ptr(), input.as_ptr()));
+        assert_eq!(cursor.as_str(), " x");
+
+        assert_eq!(cursor.consume_prefix("x"), None);
+        assert_eq!(cursor.as_str(), " x");
+    }
+
+    #[test]
+    fn test_consume_any_str() {
+        const OPERATORS: &[&str] = &["<", "<=", "<<", "=", "=="];
+        let mut cursor = Cursor::from("<=<<=x");
+        assert_eq!(cursor.consume_any_str(OPERATORS), Some("<="));
+        assert_eq!(cursor.consume_any_str(OPERATORS), Some("<<"));
+        assert_eq!(cursor.consume_any_str(OPERATORS), Some("="));
+        assert_eq!(cursor.consume_any_str(OPERATORS), None);
+        assert_eq!(cursor.as_str(), "x");
+
+        let mut cursor = Cursor::from("< 1");
+        assert_eq!(cursor.consume_any_str(OPERATORS), Some("<"));
+        assert_eq!(cursor.as_str(), " 1");
+    }
+
+    #[test]
+    fn test_line_containing() {
+        let cursor = Cursor::from("first\nsecond line\nlast");
+        assert_eq!(cursor.line_containing(0), "first");
+        assert_eq!(cursor.line_containing(5), "first");
+        assert_eq!(cursor.line_containing(6), "second line");
+        assert_eq!(cursor.line_containing(12), "second line");
+        assert_eq!(cursor.line_containing(17), "second line");
+        assert_eq!(cursor.line_containing(18), "last");
+        assert_eq!(cursor.line_containing(22), "last");
+        assert_eq!(Cursor::from("").line_containing(0), "");
+
+        let cursor = Cursor::from("a\r\nb");
+        assert_eq!(cursor.line_containing(0), "a");
+        assert_eq!(cursor.line_containing(2), "a");
+        assert_eq!(cursor.line_containing(3), "b");
+
+        let config = CursorConfig {
+            newline: NewlineStyle::CrLf,
+            ..CursorConfig::default()
+        };
+        let cursor = Cursor::with_config("a\nb\r\nc", config);
+        assert_eq!(cursor.line_containing(2), "a\nb");
+        assert_eq!(cursor.line_containing(4), "a\nb");
+        assert_eq!(cursor.line_containing(5), "c");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_line_containing_past_end() {
+        Cursor::from("ab").line_containing(3);
+    }
+
+    #[test]
+    fn test_span_context_lines() {
+        let mut cursor = Cursor::from("one\ntwo\nthree x\nfour\nfive");
+        cursor.consume_str("one\ntwo\nthree ");
+        let span = cursor.take(1);
+        assert_eq!(span.context_lines(0, 0), "three x");
+        assert_eq!(span.context_lines(1, 1), "two\nthree x\nfour");
+        assert_eq!(span.context_lines(5, 5), "one\ntwo\nthree x\nfour\nfive");
+
+        let mut cursor = Cursor::from("one\ntwo\nthree");
+        cursor.consume_line();
+        let line = cursor.focus_line();
+        assert_eq!(Span::new((), line).context_lines(0, 0), "two");
+
+        let mut cursor = Cursor::from("one\r\ntwo x\r\nthree");
+        cursor.consume_str("one\r\ntwo ");
+        let span = cursor.take(1);
+        assert_eq!(span.context_lines(0, 0), "two x");
+        assert_eq!(
+            span.context_lines(0, 0),
+            cursor.line_containing(span.position())
+        );
+        assert_eq!(span.context_lines(1, 1), "one\r\ntwo x\r\nthree");
+
+        let config = CursorConfig {
+            newline: NewlineStyle::CrLf,
+            ..CursorConfig::default()
+        };
+        let mut cursor = Cursor::with_config("a\nb x\r\nc", config);
+        cursor.consume_str("a\nb ");
+        let span = cursor.take(1);
+        assert_eq!(span.context_lines(0, 0), "a\nb x");
+        assert_eq!(span.context_lines(0, 1), "a\nb x\r\nc");
+    }
+
+    #[test]
+    fn test_consume_while_inline() {
+        let mut cursor = Cursor::from("\"closed\" \"open\nnext\"");
+        cursor.consume();
+        assert!(cursor.consume_while_inline(|ch| ch != '"').is_ok());
+        assert_eq!(cursor.as_str(), "\" \"open\nnext\"");
+
+        cursor.consume_str("\" \"");
+        assert!(cursor.consume_while_inline(|ch| ch != '"').is_err());
+        assert_eq!(cursor.as_str(), "open\nnext\"");
+
+        cursor.consume_while_inline(|ch| ch != '\n').unwrap();
+        assert_eq!(cursor.as_str(), "\nnext\"");
+    }
+
+    #[test]
+    fn test_measure_indent() {
+        let mut cursor = Cursor::from("    x");
+        assert_eq!(cursor.measure_indent(), Ok(4));
+        assert_eq!(cursor.as_str(), "x");
+        assert_eq!(cursor.measure_indent(), Ok(0));
+
+        let config = CursorConfig {
+            tab_width: 4,
+            ..CursorConfig::default()
+        };
+        let mut cursor = Cursor::with_config("  \t x", config);
+        assert_eq!(cursor.measure_indent(), Ok(5));
+
+        let config = CursorConfig {
+            strict_indent: true,
+            ..config
+        };
+        let mut cursor = Cursor::with_config("\t\tx", config);
+        assert_eq!(cursor.measure_indent(), Ok(8));
+        let mut cursor = Cursor::with_config("\t  x", config);
+        assert_eq!(cursor.measure_indent(), Err(IndentError { position: 1 }));
+        assert_eq!(cursor.position(), 0);
+    }
+
+    #[test]
+    fn test_consume_raw_string() {
+        let mut cursor = Cursor::from(r#"r"x" rest"#);
+        let raw = cursor.consume_raw_string().unwrap();
+        assert_eq!((raw.as_str(), *raw.kind(), raw.position()), ("x", 0, 2));
+        assert_eq!(cursor.as_str(), " rest");
+
+        let mut cursor = Cursor::from(r##"r#"a"b"#"##);
+        let raw = cursor.consume_raw_string().unwrap();
+        assert_eq!((raw.as_str(), *raw.kind()), (r#"a"b"#, 1));
+        assert!(cursor.is_empty());
+
+        let mut cursor = Cursor::from(r###"r##"a"#b"##"###);
+        assert_eq!(cursor.consume_raw_string().unwrap().as_str(), r##"a"#b"##);
+
+        let mut cursor = Cursor::from(r#"r#"abc"#);
+        assert_eq!(
+            cursor.consume_raw_string().unwrap_err(),
+            RawStringError::Unterminated { hashes: 1 }
+        );
+        assert_eq!(cursor.position(), 0);
+
+        for input in ["rx", "r#x", "\"x\""] {
+            let mut cursor = Cursor::from(input);
+            assert_eq!(
+                cursor.consume_raw_string().unwrap_err(),
+                RawStringError::NotRawString
+            );
+        }
+    }
+
+    #[test]
+    fn test_span_hash() {
+        let mut cursor = Cursor::from("let x = let");
+        let first = cursor.take_while(char::is_alphabetic);
+        cursor.consume_str(" x = ");
+        let second = cursor.take_while(char::is_alphabetic);
+        assert_ne!(first.position(), second.position());
+
+        let set: std::collections::HashSet<_> = [first.clone(), second].into_iter().collect();
+        assert_eq!(set.len(), 1);
+        assert!(set.contains(&Span::new((), Cursor::from("let"))));
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut cursor = Cursor::from("two passes");
+        cursor.consume_while(|_| true);
+        cursor.reset();
+        assert_eq!(cursor.position(), 0);
+        assert_eq!(cursor.as_str(), cursor.input());
+
+        let mut focused = Cursor::from("ab").focus_n(1);
+        focused.reset();
+        assert_eq!(focused.as_str(), "ab");
+    }
+
+    #[test]
+    fn test_peek_line() {
+        let mut cursor = Cursor::from("```\r\ncode\nlast");
+        assert_eq!(cursor.peek_line(), "```");
+        assert_eq!(cursor.position(), 0);
+        cursor.consume_line();
+        assert_eq!(cursor.peek_line(), "code");
+        cursor.consume_line();
+        assert_eq!(cursor.peek_line(), "last");
+        cursor.consume_line();
+        assert_eq!(cursor.peek_line(), "");
+    }
+
+    #[test]
+    fn test_consume_delimited() {
+        let mut cursor = Cursor::from("(abc) rest");
+        let (opener, inner, closer) = cursor.consume_delimited('(', ')').unwrap();
+        assert_eq!((opener.as_str(), opener.position()), ("(", 0));
+        assert_eq!((inner.as_str(), inner.position()), ("abc", 1));
+        assert_eq!((closer.as_str(), closer.position()), (")", 4));
+        assert_eq!(cursor.as_str(), " rest");
+
+        let mut cursor = Cursor::from("«a«b»»");
+        let (opener, inner, closer) = cursor.consume_delimited('«', '»').unwrap();
+        assert_eq!(
+            (opener.as_str(), inner.as_str(), closer.as_str()),
+            ("«", "a«b»", "»")
+        );
+
+        let mut cursor = Cursor::from("(a(b)");
+        assert!(cursor.consume_delimited('(', ')').is_none());
+        assert_eq!(cursor.position(), 0);
+    }
+
+    #[test]
+    fn test_consume_str_ignore_ascii_case() {
+        let mut cursor = Cursor::from("SELECT * FROM t");
+        assert!(cursor.consume_str_ignore_ascii_case("select"));
+        assert_eq!(cursor.as_str(), " * FROM t");
+        assert!(!cursor.consume_str_ignore_ascii_case(" * from tt"));
+        assert!(cursor.consume_str_ignore_ascii_case(" * from T"));
+        assert!(cursor.is_empty());
+
+        let mut cursor = Cursor::from("ÄBC");
+        assert!(!cursor.consume_str_ignore_ascii_case("äbc"));
+        assert!(cursor.consume_str_ignore_ascii_case("Äbc"));
+    }
+
+    #[test]
+    fn test_remaining_ptr_range() {
+        let input = "skip ы rest";
+        let mut cursor = Cursor::from(input);
+        cursor.consume_n(5);
+        let range = cursor.remaining_ptr_range();
+        assert_eq!(range.start, input[5..].as_ptr());
+        assert_eq!(
+            range.end as usize - range.start as usize,
+            cursor.as_str().len()
+        );
+
+        cursor.consume_while(|_| true);
+        let range = cursor.remaining_ptr_range();
+        assert_eq!(range.start, range.end);
+    }
+
+    #[test]
+    fn test_focus_word() {
+        let mut cursor = Cursor::from("  hello  world ");
+        let hello = cursor.focus_word().unwrap();
+        assert_eq!((hello.as_str(), hello.position()), ("hello", 2));
+        let world = cursor.focus_word().unwrap();
+        assert_eq!((world.as_str(), world.position()), ("world", 9));
+        assert!(cursor.focus_word().is_none());
+        assert!(cursor.is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+//! Precomputed line starts for repeated line/column lookups.
+
+use super::{Cursor, CursorConfig};
+
+/// Byte offsets of every line start in an input, so that `line_col` is a
+/// binary search instead of a scan from the start of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMap<'i> {
+    input: &'i str,
+    config: CursorConfig,
+    line_starts: Vec<usize>,
+}
+
+impl<'i> LineMap<'i> {
+    pub fn new(input: &'i str) -> Self {
+        Self::with_config(input, CursorConfig::default())
+    }
+
+    /// Map splitting lines and counting columns as `config` says.
+    pub fn with_config(input: &'i str, config: CursorConfig) -> Self {
+        let terminator = config.newline.terminator();
+        let line_starts = std::iter::once(0)
+            .chain(
+                input
+                    .match_indices(terminator)
+                    .map(|(offset, _)| offset + terminator.len()),
+            )
+            .collect();
+        Self {
+            input,
+            config,
+            line_starts,
+        }
+    }
+
+    pub fn input(&self) -> &'i str {
+        self.input
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Zero based line and column of `byte_pos`, like `Cursor::line_col`
+    /// with the same config. Panics if `byte_pos` is not a char boundary of
+    /// the input.
+    pub fn line_col(&self, byte_pos: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= byte_pos) - 1;
+        let col = self
+            .config
+            .columns(&self.input[self.line_starts[line]..byte_pos]);
+        (line, col)
+    }
+}
+
+impl<'i> Cursor<'i> {
+    /// Map of the whole input with this cursor's config.
+    pub fn line_map(&self) -> LineMap<'i> {
+        LineMap::with_config(self.input(), self.config())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NewlineStyle;
+
+    #[test]
+    fn test_line_map() {
+        let input = "fn main() {\n    let ы = '🦀';\n\n}\n";
+        let line_map = Cursor::from(input).line_map();
+        assert_eq!(line_map.line_count(), 5);
+        assert_eq!(line_map.line_col(0), (0, 0));
+        assert_eq!(line_map.line_col(12), (1, 0));
+        assert_eq!(line_map.line_col(input.len()), (4, 0));
+
+        let mut seed: u32 = 0x9E37_79B9;
+        for _ in 0..100 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+
+            let mut position = seed as usize % (input.len() + 1);
+            while !input.is_char_boundary(position) {
+                position -= 1;
+            }
+            let cursor = Cursor::new(input, input[position..].chars());
+            assert_eq!(line_map.line_col(position), cursor.line_col());
+        }
+    }
+
+    #[test]
+    fn test_line_map_config() {
+        let input = "\tfn main() {\r\n\t\tlet ы = '🦀';\n\r\n \t}\r\n";
+        let configs = [
+            CursorConfig {
+                tab_width: 4,
+                ..CursorConfig::default()
+            },
+            CursorConfig {
+                tab_width: 4,
+                newline: NewlineStyle::CrLf,
+                ..CursorConfig::default()
+            },
+            CursorConfig {
+                tab_width: 8,
+                newline: NewlineStyle::Lf,
+                ..CursorConfig::default()
+            },
+        ];
+
+        let cursor = Cursor::with_config(input, configs[0]);
+        assert_eq!(cursor.slice(1..input.len()).line_col(), (0, 4));
+        assert_eq!(cursor.line_map().line_col(1), (0, 4));
+        let cursor = Cursor::with_config("a\nb", configs[1]);
+        assert_eq!(cursor.line_map().line_col(3), (0, 3));
+
+        let mut seed: u32 = 0x9E37_79B9;
+        for config in configs {
+            let cursor = Cursor::with_config(input, config);
+            let line_map = cursor.line_map();
+            for _ in 0..100 {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+
+                let mut position = seed as usize % (input.len() + 1);
+                while !input.is_char_boundary(position) {
+                    position -= 1;
+                }
+                let at = cursor.slice(position..input.len());
+                assert_eq!(line_map.line_col(position), at.line_col(), "{config:?}");
+            }
+        }
+    }
+}
@@ -3,28 +3,41 @@
 
 // **This file is reused from rustc_lexer at #897e37553bb relicensed by MIT**
 
+use std::borrow::Cow;
+use std::fmt;
 use std::ops::Range;
 // use std::str::Chars;
 
-use super::Cursor;
+use super::char_class::hex_value;
+use super::{Cursor, Span};
 
 /// Errors and warnings that can occur during string unescaping.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum EscapeError {
-    // /// Expected 1 char, but 0 were found.
-    // ZeroChars,
-    // /// Expected 1 char, but more than 1 were found.
-    // MoreThanOneChar,
+    /// Expected 1 char, but 0 were found.
+    ZeroChars,
+    /// Expected 1 char, but more than 1 were found.
+    MoreThanOneChar,
+    /// Expected an opening quote, but found something else.
+    MissingOpeningQuote,
+    /// Input ended before the closing quote.
+    UnterminatedLiteral,
     /// Escaped '\' character without continuation.
     LoneSlash,
     /// Invalid escape character (e.g. '\z').
     InvalidEscape,
+    /// '\' followed by a newline in a char literal, which can't continue
+    /// onto the next line.
+    LineContinuationInChar,
     /// Raw '\r' encountered.
     BareCarriageReturn,
     // /// Raw '\r' encountered in raw string.
     // BareCarriageReturnInRawString,
     /// Unescaped character that was expected to be escaped (e.g. raw '\t').
     EscapeOnlyChar,
+    /// Unescaped quote of the other kind in a strict mode (e.g. raw '\'' in
+    /// a strict double quoted literal).
+    UnescapedOppositeQuote,
 
     /// Numeric character escape is too short (e.g. '\x1').
     TooShortHexEscape,
@@ -63,38 +76,370 @@ pub enum EscapeError {
     // MultipleSkippedLinesWarning,
 }
 
+impl EscapeError {
+    /// Whether the literal can't be given a sensible value anymore.
+    ///
+    /// Errors are minor when keeping the offending text verbatim is an obvious
+    /// recovery: a bare '\r' or stray quote, or an unknown simple escape like
+    /// '\z'.
+    /// Malformed numeric escapes, invalid code points and broken literal
+    /// structure are fatal.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(
+            self,
+            EscapeError::BareCarriageReturn
+                | EscapeError::EscapeOnlyChar
+                | EscapeError::UnescapedOppositeQuote
+                | EscapeError::InvalidEscape
+                | EscapeError::LineContinuationInChar
+        )
+    }
+
+    pub fn category(&self) -> EscapeErrorCategory {
+        match self {
+            EscapeError::ZeroChars
+            | EscapeError::MoreThanOneChar
+            | EscapeError::MissingOpeningQuote
+            | EscapeError::UnterminatedLiteral => EscapeErrorCategory::Literal,
+            EscapeError::LoneSlash => EscapeErrorCategory::Slash,
+            EscapeError::InvalidEscape | EscapeError::LineContinuationInChar => {
+                EscapeErrorCategory::SimpleEscape
+            }
+            EscapeError::BareCarriageReturn
+            | EscapeError::EscapeOnlyChar
+            | EscapeError::UnescapedOppositeQuote => EscapeErrorCategory::BareChar,
+            EscapeError::TooShortHexEscape | EscapeError::InvalidCharInHexEscape => {
+                EscapeErrorCategory::HexEscape
+            }
+            EscapeError::NoBraceInUnicodeEscape
+            | EscapeError::InvalidCharInUnicodeEscape
+            | EscapeError::EmptyUnicodeEscape
+            | EscapeError::UnclosedUnicodeEscape
+            | EscapeError::LeadingUnderscoreUnicodeEscape
+            | EscapeError::OverlongUnicodeEscape
+            | EscapeError::LoneSurrogateUnicodeEscape
+            | EscapeError::OutOfRangeUnicodeEscape => EscapeErrorCategory::UnicodeEscape,
+        }
+    }
+
+    /// Numeric code of this error, for storing or reporting it compactly.
+    ///
+    /// Codes are stable: a variant keeps its code across releases, and new
+    /// variants get new codes. `0` is never used.
+    pub fn code(&self) -> u16 {
+        match self {
+            EscapeError::ZeroChars => 1,
+            EscapeError::MoreThanOneChar => 2,
+            EscapeError::MissingOpeningQuote => 3,
+            EscapeError::UnterminatedLiteral => 4,
+            EscapeError::LoneSlash => 5,
+            EscapeError::InvalidEscape => 6,
+            EscapeError::BareCarriageReturn => 7,
+            EscapeError::EscapeOnlyChar => 8,
+            EscapeError::UnescapedOppositeQuote => 9,
+            EscapeError::TooShortHexEscape => 10,
+            EscapeError::InvalidCharInHexEscape => 11,
+            EscapeError::NoBraceInUnicodeEscape => 12,
+            EscapeError::InvalidCharInUnicodeEscape => 13,
+            EscapeError::EmptyUnicodeEscape => 14,
+            EscapeError::UnclosedUnicodeEscape => 15,
+            EscapeError::LeadingUnderscoreUnicodeEscape => 16,
+            EscapeError::OverlongUnicodeEscape => 17,
+            EscapeError::LoneSurrogateUnicodeEscape => 18,
+            EscapeError::OutOfRangeUnicodeEscape => 19,
+            EscapeError::LineContinuationInChar => 20,
+        }
+    }
+
+    /// Inverse of `code`, `None` for codes not assigned to any error.
+    pub fn from_code(code: u16) -> Option<Self> {
+        Some(match code {
+            1 => EscapeError::ZeroChars,
+            2 => EscapeError::MoreThanOneChar,
+            3 => EscapeError::MissingOpeningQuote,
+            4 => EscapeError::UnterminatedLiteral,
+            5 => EscapeError::LoneSlash,
+            6 => EscapeError::InvalidEscape,
+            7 => EscapeError::BareCarriageReturn,
+            8 => EscapeError::EscapeOnlyChar,
+            9 => EscapeError::UnescapedOppositeQuote,
+            10 => EscapeError::TooShortHexEscape,
+            11 => EscapeError::InvalidCharInHexEscape,
+            12 => EscapeError::NoBraceInUnicodeEscape,
+            13 => EscapeError::InvalidCharInUnicodeEscape,
+            14 => EscapeError::EmptyUnicodeEscape,
+            15 => EscapeError::UnclosedUnicodeEscape,
+            16 => EscapeError::LeadingUnderscoreUnicodeEscape,
+            17 => EscapeError::OverlongUnicodeEscape,
+            18 => EscapeError::LoneSurrogateUnicodeEscape,
+            19 => EscapeError::OutOfRangeUnicodeEscape,
+            20 => EscapeError::LineContinuationInChar,
+            _ => return None,
+        })
+    }
+}
+
+/// Family of an `EscapeError`, for help text shared by related errors.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EscapeErrorCategory {
+    /// The literal as a whole is malformed: wrong char count or quotes.
+    Literal,
+    /// A '\' with nothing after it.
+    Slash,
+    /// A '\' followed by a char that doesn't form an escape.
+    SimpleEscape,
+    /// A '\x' escape.
+    HexEscape,
+    /// A '\u' escape.
+    UnicodeEscape,
+    /// A char that has to be escaped appeared raw.
+    BareChar,
+}
+
 /// What kind of literal do we parse.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     Single,
     Double,
+    /// Double quoted, with JSON's simple escapes, `\uXXXX` escapes and UTF-16
+    /// surrogate pairs, and without `\x` escapes.
+    Json,
+    /// Single quoted, where a '"' has to be escaped as well.
+    SingleStrict,
+    /// Double quoted, where a '\'' has to be escaped as well.
+    DoubleStrict,
+}
+
+/// What a run of a literal's contents is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeKind {
+    /// Unescaped chars.
+    Literal,
+    /// An escape like '\n'.
+    Simple,
+    /// '\xNN'
+    Hex,
+    /// '\u{..}', or '\uXXXX' in JSON.
+    Unicode,
+}
+
+impl Mode {
+    /// Mode of a literal opened by `quote`. JSON strings have to be asked for
+    /// explicitly.
+    pub fn from_quote(quote: char) -> Option<Mode> {
+        match quote {
+            '\'' => Some(Mode::Single),
+            '"' => Some(Mode::Double),
+            _ => None,
+        }
+    }
+
+    /// The quote delimiting literals of this mode.
+    pub fn quote(self) -> char {
+        match self {
+            Mode::Single | Mode::SingleStrict => '\'',
+            Mode::Double | Mode::Json | Mode::DoubleStrict => '"',
+        }
+    }
+
+    /// Whether the other kind of quote has to be escaped too.
+    pub fn is_strict(self) -> bool {
+        matches!(self, Mode::SingleStrict | Mode::DoubleStrict)
+    }
+}
+
+/// Simple escapes, mapping the character after '\\' to its replacement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapeTable {
+    ascii: [Option<char>; 128],
+}
+
+impl EscapeTable {
+    /// A table without any simple escapes.
+    pub fn new() -> Self {
+        Self { ascii: [None; 128] }
+    }
+
+    /// The simple escapes accepted by Rust string literals.
+    pub fn rust() -> Self {
+        Self::new()
+            .with('"', '"')
+            .with('n', '\n')
+            .with('r', '\r')
+            .with('t', '\t')
+            .with('\\', '\\')
+            .with('\'', '\'')
+            .with('0', '\0')
+    }
+
+    /// The simple escapes accepted by JSON strings.
+    pub fn json() -> Self {
+        Self::new()
+            .with('"', '"')
+            .with('\\', '\\')
+            .with('/', '/')
+            .with('b', '\u{8}')
+            .with('f', '\u{C}')
+            .with('n', '\n')
+            .with('r', '\r')
+            .with('t', '\t')
+    }
+
+    /// Adds or replaces the escape `\<escape>`, which must be ASCII.
+    pub fn with(mut self, escape: char, replacement: char) -> Self {
+        assert!(escape.is_ascii(), "escape character must be ASCII");
+        self.ascii[escape as usize] = Some(replacement);
+        self
+    }
+
+    pub fn get(&self, escape: char) -> Option<char> {
+        self.ascii.get(escape as usize).copied().flatten()
+    }
+}
+
+/// The empty table, same as `new`. Use `rust` or `json` for a preset.
+impl Default for EscapeTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'i> Cursor<'i> {
+    /// Consumes a quoted literal, returning its contents tagged with the mode
+    /// of its quote. A backslash always escapes the following char, so only
+    /// the structure is checked, not the escapes themselves. On error nothing
+    /// is consumed.
+    pub fn consume_quoted(&mut self) -> Result<Span<'i, Mode>, EscapeError> {
+        let quote = self.first().ok_or(EscapeError::MissingOpeningQuote)?;
+        let mode = Mode::from_quote(quote).ok_or(EscapeError::MissingOpeningQuote)?;
+
+        let mut cursor = self.clone();
+        cursor.consume();
+        let start = cursor.position();
+
+        loop {
+            let end = cursor.position();
+            match cursor.consume() {
+                None => return Err(EscapeError::UnterminatedLiteral),
+                Some('\\') => {
+                    cursor.consume();
+                }
+                Some(c) if c == quote => {
+                    *self = cursor;
+                    self.check_invariants();
+                    return Ok(Span::new(mode, self.slice(start..end)));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+/// Scans exactly `digits` hex digits, as after a `\x` or JSON's `\u`.
+/// Panics if `digits` is more than 8, which wouldn't fit a `u32`.
+pub fn scan_hex_escape(cursor: &mut Cursor, digits: usize) -> Result<u32, EscapeError> {
+    assert!(digits <= 8, "more hex digits than fit a u32");
+    let mut value = 0;
+    for _ in 0..digits {
+        let c = cursor.consume().ok_or(EscapeError::TooShortHexEscape)?;
+        let digit = hex_value(c).ok_or(EscapeError::InvalidCharInHexEscape)?;
+        value = value * 16 + u32::from(digit);
+    }
+    Ok(value)
 }
 
-fn scan_escape(cursor: &mut Cursor) -> Result<char, EscapeError> {
+fn scan_json_unicode_escape(cursor: &mut Cursor) -> Result<char, EscapeError> {
+    // We've parsed '\u', now we have to parse 'XXXX'.
+    let value = scan_hex_escape(cursor, 4)?;
+
+    if let 0xD800..=0xDBFF = value {
+        // A high surrogate is only valid when directly followed by an escaped
+        // low surrogate. Don't consume anything if what follows doesn't fit.
+        let mut low_cursor = cursor.clone();
+        if low_cursor.consume() == Some('\\') && low_cursor.consume() == Some('u') {
+            if let Ok(low @ 0xDC00..=0xDFFF) = scan_hex_escape(&mut low_cursor, 4) {
+                *cursor = low_cursor;
+                let value = 0x10000 + ((value - 0xD800) << 10) + (low - 0xDC00);
+                return std::char::from_u32(value).ok_or(EscapeError::OutOfRangeUnicodeEscape);
+            }
+        }
+    }
+
+    std::char::from_u32(value).ok_or(EscapeError::LoneSurrogateUnicodeEscape)
+}
+
+/// Scans the `{..}` of a Rust `\u{..}` escape, of up to 6 hex digits with
+/// `_` separators, into the char it denotes.
+pub fn scan_unicode_brace_escape(cursor: &mut Cursor) -> Result<char, EscapeError> {
+    if cursor.consume() != Some('{') {
+        return Err(EscapeError::NoBraceInUnicodeEscape);
+    }
+
+    // First character must be a hexadecimal digit.
+    let mut n_digits = 1;
+    let mut value: u32 = match cursor.consume().ok_or(EscapeError::UnclosedUnicodeEscape)? {
+        '_' => return Err(EscapeError::LeadingUnderscoreUnicodeEscape),
+        '}' => return Err(EscapeError::EmptyUnicodeEscape),
+        c => c
+            .to_digit(16)
+            .ok_or(EscapeError::InvalidCharInUnicodeEscape)?,
+    };
+
+    // First character is valid, now parse the rest of the number
+    // and closing brace.
+    loop {
+        match cursor.consume() {
+            None => return Err(EscapeError::UnclosedUnicodeEscape),
+            Some('_') => continue,
+            Some('}') => {
+                if n_digits > 6 {
+                    return Err(EscapeError::OverlongUnicodeEscape);
+                }
+
+                // Incorrect syntax has higher priority for error reporting
+                // than unallowed value for a literal.
+                // if mode.is_bytes() {
+                //     return Err(EscapeError::UnicodeEscapeInByte);
+                // }
+
+                return std::char::from_u32(value).ok_or({
+                    if value > 0x10FFFF {
+                        EscapeError::OutOfRangeUnicodeEscape
+                    } else {
+                        EscapeError::LoneSurrogateUnicodeEscape
+                    }
+                });
+            }
+            Some(c) => {
+                let digit = c
+                    .to_digit(16)
+                    .ok_or(EscapeError::InvalidCharInUnicodeEscape)?;
+                n_digits += 1;
+                if n_digits > 6 {
+                    // Stop updating value since we're sure that it's incorrect already.
+                    continue;
+                }
+                // let digit = digit as u32;
+                value = value * 16 + digit;
+            }
+        };
+    }
+}
+
+fn scan_escape(cursor: &mut Cursor, mode: Mode, table: &EscapeTable) -> Result<char, EscapeError> {
     // Previous character was '\\', unescape what follows.
     debug_assert_eq!(cursor.previous(), '\\');
 
     let second_char = cursor.consume().ok_or(EscapeError::LoneSlash)?;
 
+    if let Some(res) = table.get(second_char) {
+        return Ok(res);
+    }
+
     let res = match second_char {
-        '"' => '"',
-        'n' => '\n',
-        'r' => '\r',
-        't' => '\t',
-        '\\' => '\\',
-        '\'' => '\'',
-        '0' => '\0',
-
-        'x' => {
+        'x' if mode != Mode::Json => {
             // Parse hexadecimal character code.
-
-            let hi = cursor.consume().ok_or(EscapeError::TooShortHexEscape)?;
-            let hi = hi.to_digit(16).ok_or(EscapeError::InvalidCharInHexEscape)?;
-
-            let lo = cursor.consume().ok_or(EscapeError::TooShortHexEscape)?;
-            let lo = lo.to_digit(16).ok_or(EscapeError::InvalidCharInHexEscape)?;
-
-            let value = hi * 16 + lo;
+            let value = scan_hex_escape(cursor, 2)?;
 
             // For a byte literal verify that it is within ASCII range.
             // if !mode.is_bytes() && !is_ascii(value) {
@@ -105,63 +450,15 @@ fn scan_escape(cursor: &mut Cursor) -> Result<char, EscapeError> {
             value as u8 as char
         }
 
-        'u' => {
-            // We've parsed '\u', now we have to parse '{..}'.
-
-            if cursor.consume() != Some('{') {
-                return Err(EscapeError::NoBraceInUnicodeEscape);
-            }
-
-            // First character must be a hexadecimal digit.
-            let mut n_digits = 1;
-            let mut value: u32 = match cursor.consume().ok_or(EscapeError::UnclosedUnicodeEscape)? {
-                '_' => return Err(EscapeError::LeadingUnderscoreUnicodeEscape),
-                '}' => return Err(EscapeError::EmptyUnicodeEscape),
-                c => c
-                    .to_digit(16)
-                    .ok_or(EscapeError::InvalidCharInUnicodeEscape)?,
-            };
+        'u' if mode == Mode::Json => scan_json_unicode_escape(cursor)?,
 
-            // First character is valid, now parse the rest of the number
-            // and closing brace.
-            loop {
-                match cursor.consume() {
-                    None => return Err(EscapeError::UnclosedUnicodeEscape),
-                    Some('_') => continue,
-                    Some('}') => {
-                        if n_digits > 6 {
-                            return Err(EscapeError::OverlongUnicodeEscape);
-                        }
-
-                        // Incorrect syntax has higher priority for error reporting
-                        // than unallowed value for a literal.
-                        // if mode.is_bytes() {
-                        //     return Err(EscapeError::UnicodeEscapeInByte);
-                        // }
-
-                        break std::char::from_u32(value).ok_or_else(|| {
-                            if value > 0x10FFFF {
-                                EscapeError::OutOfRangeUnicodeEscape
-                            } else {
-                                EscapeError::LoneSurrogateUnicodeEscape
-                            }
-                        })?;
-                    }
-                    Some(c) => {
-                        let digit = c
-                            .to_digit(16)
-                            .ok_or(EscapeError::InvalidCharInUnicodeEscape)?;
-                        n_digits += 1;
-                        if n_digits > 6 {
-                            // Stop updating value since we're sure that it's incorrect already.
-                            continue;
-                        }
-                        // let digit = digit as u32;
-                        value = value * 16 + digit;
-                    }
-                };
-            }
+        '\n' if mode.quote() == '\'' => return Err(EscapeError::LineContinuationInChar),
+        '\r' if mode.quote() == '\'' && cursor.first() == Some('\n') => {
+            cursor.consume();
+            return Err(EscapeError::LineContinuationInChar);
         }
+
+        'u' => scan_unicode_brace_escape(cursor)?,
         _ => return Err(EscapeError::InvalidEscape),
     };
     Ok(res)
@@ -169,7 +466,56 @@ fn scan_escape(cursor: &mut Cursor) -> Result<char, EscapeError> {
 
 /// Takes a contents of a string literal (without quotes) and produces a
 /// sequence of escaped characters or errors.
+///
+/// Scanning always resumes right after the reported range, which covers
+/// exactly what was consumed:
+///
+/// - `LoneSlash`, `TooShortHexEscape` and `UnclosedUnicodeEscape` only occur
+///   at the end of input and cover the rest of it.
+/// - `BareCarriageReturn`, `EscapeOnlyChar` and `UnescapedOppositeQuote`
+///   cover the offending char.
+/// - `InvalidEscape` covers the `\` and the char after it.
+/// - `LineContinuationInChar` covers the `\` and the `\n` or `\r\n` after
+///   it.
+/// - `NoBraceInUnicodeEscape` covers the `\u` and the char that should have
+///   been `{`, or just `\u` at the end of input.
+/// - `InvalidCharInHexEscape`, `InvalidCharInUnicodeEscape` and
+///   `LeadingUnderscoreUnicodeEscape` cover up to and including the
+///   offending char.
+/// - `EmptyUnicodeEscape`, `OverlongUnicodeEscape`,
+///   `LoneSurrogateUnicodeEscape` and `OutOfRangeUnicodeEscape` cover the
+///   whole escape, including the closing brace if there is one.
 pub fn unescape_str<F>(mut cursor: Cursor, mode: Mode, callback: &mut F)
+where
+    F: FnMut(Range<usize>, Result<char, EscapeError>),
+{
+    unescape_str_resume(&mut cursor, mode, callback)
+}
+
+/// Like [`unescape_str`], but leaves `cursor` where scanning stopped, e.g.
+/// at the closing quote when it was focused on a literal's contents. Ranges
+/// are still relative to where the cursor started.
+pub fn unescape_str_resume<F>(cursor: &mut Cursor, mode: Mode, callback: &mut F)
+where
+    F: FnMut(Range<usize>, Result<char, EscapeError>),
+{
+    let table = match mode {
+        Mode::Json => EscapeTable::json(),
+        _ => EscapeTable::rust(),
+    };
+    unescape_cursor(cursor, mode, &table, callback)
+}
+
+/// Like [`unescape_str`], but simple escapes are looked up in `table`.
+/// Numeric escapes are still recognized unless the table overrides them.
+pub fn unescape_str_with<F>(mut cursor: Cursor, mode: Mode, table: &EscapeTable, callback: &mut F)
+where
+    F: FnMut(Range<usize>, Result<char, EscapeError>),
+{
+    unescape_cursor(&mut cursor, mode, table, callback)
+}
+
+fn unescape_cursor<F>(cursor: &mut Cursor, mode: Mode, table: &EscapeTable, callback: &mut F)
 where
     F: FnMut(Range<usize>, Result<char, EscapeError>),
 {
@@ -178,11 +524,11 @@ where
         let start = initial_len - cursor.as_str().len() - first_char.len_utf8();
 
         let unescaped_char = match first_char {
-            '\\' => scan_escape(&mut cursor),
+            '\\' => scan_escape(cursor, mode, table),
             '\n' => Ok('\n'),
             '\t' => Ok('\t'),
-            '"' if mode == Mode::Double => Err(EscapeError::EscapeOnlyChar),
-            '\'' if mode == Mode::Single => Err(EscapeError::EscapeOnlyChar),
+            c if c == mode.quote() => Err(EscapeError::EscapeOnlyChar),
+            '"' | '\'' if mode.is_strict() => Err(EscapeError::UnescapedOppositeQuote),
             '\r' => Err(EscapeError::BareCarriageReturn),
             _ => Ok(first_char),
         };
@@ -191,25 +537,183 @@ where
     }
 }
 
-#[cfg(tset)]
+/// Takes a contents of a char literal (without quotes), and returns an
+/// unescaped char or an error.
+pub fn unescape_char(input: &str, mode: Mode) -> Result<char, EscapeError> {
+    let mut result = None;
+    unescape_str(Cursor::from(input), mode, &mut |_range, c| {
+        result = Some(match result {
+            None => c,
+            Some(Ok(_)) => Err(EscapeError::MoreThanOneChar),
+            Some(Err(e)) => Err(e),
+        });
+    });
+    result.unwrap_or(Err(EscapeError::ZeroChars))
+}
+
+/// Like `unescape_to_string`, but borrows `input` when it has nothing to
+/// unescape or reject, which is the common case.
+pub fn unescape_cow(input: &str, mode: Mode) -> Result<Cow<'_, str>, (Range<usize>, EscapeError)> {
+    if needs_unescaping(input, mode) {
+        unescape_to_string(input, mode).map(Cow::Owned)
+    } else {
+        Ok(Cow::Borrowed(input))
+    }
+}
+
+/// Whether `input` has chars that `unescape_cursor` doesn't map to
+/// themselves. All of them are ASCII, so bytes can be checked.
+fn needs_unescaping(input: &str, mode: Mode) -> bool {
+    let quote = mode.quote() as u8;
+    input.bytes().any(|byte| match byte {
+        b'\\' | b'\r' => true,
+        b'"' | b'\'' => byte == quote || mode.is_strict(),
+        _ => false,
+    })
+}
+
+/// Unescapes the contents of a string literal, stopping at the first error.
+///
+/// The buffer is preallocated to `input.len()` bytes. Every escape sequence is
+/// at least as long as the character it produces, so the result never needs
+/// to grow.
+pub fn unescape_to_string(input: &str, mode: Mode) -> Result<String, (Range<usize>, EscapeError)> {
+    let mut buf = String::with_capacity(input.len());
+    unescape_into(input, mode, &mut buf)?;
+    Ok(buf)
+}
+
+/// Unescapes the contents of a string literal, appending to `out` and
+/// stopping at the first error.
+///
+/// Chars unescaped before the error are left in `out`.
+pub fn unescape_into(
+    input: &str,
+    mode: Mode,
+    out: &mut String,
+) -> Result<(), (Range<usize>, EscapeError)> {
+    unescape_write(input, mode, out).map_err(|e| match e {
+        UnescapeWriteError::Escape(range, e) => (range, e),
+        UnescapeWriteError::Fmt(_) => unreachable!("writing to a String can't fail"),
+    })
+}
+
+/// Error of [`unescape_write`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnescapeWriteError {
+    /// The literal is invalid at the given range.
+    Escape(Range<usize>, EscapeError),
+    /// The writer failed.
+    Fmt(fmt::Error),
+}
+
+impl From<fmt::Error> for UnescapeWriteError {
+    fn from(e: fmt::Error) -> Self {
+        UnescapeWriteError::Fmt(e)
+    }
+}
+
+/// Unescapes the contents of a string literal into `out`, stopping at the
+/// first escape or write error. Chars written before the error are left in
+/// `out`.
+pub fn unescape_write<W: fmt::Write>(
+    input: &str,
+    mode: Mode,
+    out: &mut W,
+) -> Result<(), UnescapeWriteError> {
+    let mut result = Ok(());
+    unescape_str(Cursor::from(input), mode, &mut |range, c| {
+        if result.is_ok() {
+            result = match c {
+                Ok(c) => out.write_char(c).map_err(UnescapeWriteError::from),
+                Err(e) => Err(UnescapeWriteError::Escape(range, e)),
+            };
+        }
+    });
+    result
+}
+
+/// Unescapes the contents of a string literal without stopping at errors.
+/// Each error is replaced by U+FFFD in the result and collected with its
+/// range.
+pub fn unescape_lossy(input: &str, mode: Mode) -> (String, Vec<(Range<usize>, EscapeError)>) {
+    let mut buf = String::with_capacity(input.len());
+    let mut errors = Vec::new();
+    unescape_str(Cursor::from(input), mode, &mut |range, c| match c {
+        Ok(c) => buf.push(c),
+        Err(e) => {
+            buf.push(char::REPLACEMENT_CHARACTER);
+            errors.push((range, e));
+        }
+    });
+    (buf, errors)
+}
+
+/// Like `unescape_lossy`, but separates fatal errors from the minor ones that
+/// `is_fatal` rejects, returned as warnings. The text of a warning is kept
+/// verbatim in the result.
+#[allow(clippy::type_complexity)]
+pub fn partition_escapes(
+    input: &str,
+    mode: Mode,
+) -> (
+    String,
+    Vec<(Range<usize>, EscapeError)>,
+    Vec<(Range<usize>, EscapeError)>,
+) {
+    let mut buf = String::with_capacity(input.len());
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    unescape_str(Cursor::from(input), mode, &mut |range, c| match c {
+        Ok(c) => buf.push(c),
+        Err(e) if e.is_fatal() => {
+            buf.push(char::REPLACEMENT_CHARACTER);
+            errors.push((range, e));
+        }
+        Err(e) => {
+            buf.push_str(&input[range.clone()]);
+            warnings.push((range, e));
+        }
+    });
+    (buf, errors, warnings)
+}
+
+/// Splits the contents of a string literal into runs of literal text and
+/// individual escapes. Invalid escapes are classified by the char after the
+/// '\\', as far as it goes.
+pub fn classify_escapes(input: &str, mode: Mode) -> Vec<(Range<usize>, EscapeKind)> {
+    let mut runs: Vec<(Range<usize>, EscapeKind)> = Vec::new();
+    unescape_str(Cursor::from(input), mode, &mut |range, _| {
+        let mut chars = input[range.clone()].chars();
+        let kind = match (chars.next(), chars.next()) {
+            (Some('\\'), Some('x')) => EscapeKind::Hex,
+            (Some('\\'), Some('u')) => EscapeKind::Unicode,
+            (Some('\\'), _) => EscapeKind::Simple,
+            _ => EscapeKind::Literal,
+        };
+
+        match runs.last_mut() {
+            Some((last, EscapeKind::Literal)) if kind == EscapeKind::Literal => {
+                last.end = range.end
+            }
+            _ => runs.push((range, kind)),
+        }
+    });
+    runs
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_unescape_char_bad() {
         fn check(literal_text: &str, expected_error: EscapeError) {
-            let mut actual_results = vec![];
-            unescape_str(
-                Cursor::from(literal_text),
-                Mode::Double,
-                &mut |_range, actual_result| {
-                    actual_results.push(actual_result);
-                },
-            );
-            assert_eq!(actual_results[0], Err(expected_error));
+            let actual_result = unescape_char(literal_text, Mode::Double);
+            assert_eq!(actual_result, Err(expected_error));
         }
 
-        // check("", EscapeError::ZeroChars);
+        check("", EscapeError::ZeroChars);
         check(r"\", EscapeError::LoneSlash);
 
         // check("\n", EscapeError::EscapeOnlyChar);
@@ -217,17 +721,17 @@ mod tests {
         // check("'", EscapeError::EscapeOnlyChar);
         check("\r", EscapeError::BareCarriageReturn);
 
-        // check("spam", EscapeError::MoreThanOneChar);
-        // check(r"\x0ff", EscapeError::MoreThanOneChar);
-        // check(r#"\"a"#, EscapeError::MoreThanOneChar);
-        // check(r"\na", EscapeError::MoreThanOneChar);
-        // check(r"\ra", EscapeError::MoreThanOneChar);
-        // check(r"\ta", EscapeError::MoreThanOneChar);
-        // check(r"\\a", EscapeError::MoreThanOneChar);
-        // check(r"\'a", EscapeError::MoreThanOneChar);
-        // check(r"\0a", EscapeError::MoreThanOneChar);
-        // check(r"\u{0}x", EscapeError::MoreThanOneChar);
-        // check(r"\u{1F63b}}", EscapeError::MoreThanOneChar);
+        check("spam", EscapeError::MoreThanOneChar);
+        check(r"\x0ff", EscapeError::MoreThanOneChar);
+        check(r#"\"a"#, EscapeError::MoreThanOneChar);
+        check(r"\na", EscapeError::MoreThanOneChar);
+        check(r"\ra", EscapeError::MoreThanOneChar);
+        check(r"\ta", EscapeError::MoreThanOneChar);
+        check(r"\\a", EscapeError::MoreThanOneChar);
+        check(r"\'a", EscapeError::MoreThanOneChar);
+        check(r"\0a", EscapeError::MoreThanOneChar);
+        check(r"\u{0}x", EscapeError::MoreThanOneChar);
+        check(r"\u{1F63b}}", EscapeError::MoreThanOneChar);
 
         check(r"\v", EscapeError::InvalidEscape);
         check(r"\💩", EscapeError::InvalidEscape);
@@ -307,6 +811,7 @@ mod tests {
 
     #[test]
     fn test_unescape_str_warn() {
+        #[allow(dead_code)]
         fn check(literal: &str, expected: &[(Range<usize>, Result<char, EscapeError>)]) {
             let mut unescaped = Vec::with_capacity(literal.len());
             unescape_str(Cursor::from(literal), Mode::Double, &mut |range, res| {
@@ -337,6 +842,101 @@ mod tests {
         // );
     }
 
+    #[test]
+    fn test_unescape_json() {
+        fn check(literal: &str, expected: &[(Range<usize>, Result<char, EscapeError>)]) {
+            let mut unescaped = Vec::with_capacity(literal.len());
+            unescape_str(Cursor::from(literal), Mode::Json, &mut |range, res| {
+                unescaped.push((range, res))
+            });
+            assert_eq!(unescaped, expected);
+        }
+
+        check(r"\u0041", &[(0..6, Ok('A'))]);
+        check(r"\uD83D\uDE00", &[(0..12, Ok('😀'))]);
+        check(
+            r"\uD83Dx",
+            &[
+                (0..6, Err(EscapeError::LoneSurrogateUnicodeEscape)),
+                (6..7, Ok('x')),
+            ],
+        );
+        check(
+            r"\uD83D\u0041",
+            &[
+                (0..6, Err(EscapeError::LoneSurrogateUnicodeEscape)),
+                (6..12, Ok('A')),
+            ],
+        );
+        check(
+            r"\uDE00",
+            &[(0..6, Err(EscapeError::LoneSurrogateUnicodeEscape))],
+        );
+        check(r"\u004", &[(0..5, Err(EscapeError::TooShortHexEscape))]);
+        check(
+            r#"\"\\\/\b\f\n\r\t"#,
+            &[
+                (0..2, Ok('"')),
+                (2..4, Ok('\\')),
+                (4..6, Ok('/')),
+                (6..8, Ok('\u{8}')),
+                (8..10, Ok('\u{C}')),
+                (10..12, Ok('\n')),
+                (12..14, Ok('\r')),
+                (14..16, Ok('\t')),
+            ],
+        );
+        check(r"\'", &[(0..2, Err(EscapeError::InvalidEscape))]);
+        check(r"\0", &[(0..2, Err(EscapeError::InvalidEscape))]);
+        check(
+            r"\x41",
+            &[
+                (0..2, Err(EscapeError::InvalidEscape)),
+                (2..3, Ok('4')),
+                (3..4, Ok('1')),
+            ],
+        );
+        check(
+            r"\u{41}",
+            &[
+                (0..3, Err(EscapeError::InvalidCharInHexEscape)),
+                (3..4, Ok('4')),
+                (4..5, Ok('1')),
+                (5..6, Ok('}')),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_unescape_str_with() {
+        fn check(literal: &str, expected: &[Result<char, EscapeError>]) {
+            let table = EscapeTable::new()
+                .with('q', '\u{a0}')
+                .with('/', '/')
+                .with('\\', '\\');
+            let mut unescaped = Vec::with_capacity(literal.len());
+            unescape_str_with(
+                Cursor::from(literal),
+                Mode::Double,
+                &table,
+                &mut |_, res| unescaped.push(res),
+            );
+            assert_eq!(unescaped, expected);
+        }
+
+        check(r"a\qb", &[Ok('a'), Ok('\u{a0}'), Ok('b')]);
+        check(r"\/\\", &[Ok('/'), Ok('\\')]);
+        check(r"\x41\u{42}", &[Ok('A'), Ok('B')]);
+        check(r"\n", &[Err(EscapeError::InvalidEscape)]);
+        check(r"\z", &[Err(EscapeError::InvalidEscape)]);
+        check(r"\💩", &[Err(EscapeError::InvalidEscape)]);
+
+        assert_eq!(EscapeTable::default(), EscapeTable::new());
+        assert_eq!(EscapeTable::default().get('n'), None);
+        assert_eq!(EscapeTable::rust().get('n'), Some('\n'));
+        assert_eq!(EscapeTable::rust().get('q'), None);
+    }
+
     #[test]
     fn test_unescape_str_good() {
         fn check(literal_text: &str, expected: &str) {
@@ -360,4 +960,399 @@ mod tests {
         // check("hello \\\n     world", "hello world");
         check("thread's", "thread's")
     }
+
+    #[test]
+    fn test_unescape_to_string() {
+        let literal = "abcdefghij".repeat(100);
+        let unescaped = unescape_to_string(&literal, Mode::Double).unwrap();
+        assert_eq!(unescaped, literal);
+        assert!(unescaped.capacity() >= literal.len());
+
+        assert_eq!(
+            unescape_to_string(r"a\x41\u{1F63b}", Mode::Double),
+            Ok("aA😻".to_string())
+        );
+        assert_eq!(
+            unescape_to_string(r"a\qb\z", Mode::Double),
+            Err((1..3, EscapeError::InvalidEscape))
+        );
+    }
+
+    #[test]
+    fn test_unescape_lossy() {
+        let (unescaped, errors) = unescape_lossy(r#"a\qb\x4"c\u{110000}d\u[x"#, Mode::Double);
+        assert_eq!(unescaped, "a�b�c�d�x");
+        assert_eq!(
+            errors,
+            [
+                (1..3, EscapeError::InvalidEscape),
+                (4..8, EscapeError::InvalidCharInHexEscape),
+                (9..19, EscapeError::OutOfRangeUnicodeEscape),
+                (20..23, EscapeError::NoBraceInUnicodeEscape),
+            ]
+        );
+
+        let (unescaped, errors) = unescape_lossy(r"\u", Mode::Double);
+        assert_eq!(unescaped, "�");
+        assert_eq!(errors, [(0..2, EscapeError::NoBraceInUnicodeEscape)]);
+
+        let (unescaped, errors) = unescape_lossy("ok", Mode::Double);
+        assert_eq!(unescaped, "ok");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_unescape_into() {
+        let mut buf = String::new();
+        assert_eq!(unescape_into(r"a\tb", Mode::Double, &mut buf), Ok(()));
+        assert_eq!(unescape_into(r"\u{41}c", Mode::Double, &mut buf), Ok(()));
+        assert_eq!(buf, "a\tbAc");
+
+        assert_eq!(
+            unescape_into(r"de\qf", Mode::Double, &mut buf),
+            Err((2..4, EscapeError::InvalidEscape))
+        );
+        assert_eq!(buf, "a\tbAcde");
+    }
+
+    #[test]
+    fn test_mode_from_quote() {
+        assert_eq!(Mode::from_quote('\''), Some(Mode::Single));
+        assert_eq!(Mode::from_quote('"'), Some(Mode::Double));
+        assert_eq!(Mode::from_quote('`'), None);
+    }
+
+    #[test]
+    fn test_consume_quoted() {
+        let mut cursor = Cursor::from(r#""a\"b" 'c'"#);
+        let literal = cursor.consume_quoted().unwrap();
+        assert_eq!(literal.kind, Mode::Double);
+        assert_eq!(literal.as_str(), r#"a\"b"#);
+        assert_eq!(literal.position(), 1);
+        assert_eq!(cursor.as_str(), " 'c'");
+
+        cursor.consume();
+        let literal = cursor.consume_quoted().unwrap();
+        assert_eq!(literal.kind, Mode::Single);
+        assert_eq!(literal.as_str(), "c");
+        assert!(cursor.is_empty());
+
+        let mut cursor = Cursor::from(r#""abc"#);
+        assert_eq!(
+            cursor.consume_quoted().unwrap_err(),
+            EscapeError::UnterminatedLiteral
+        );
+        assert_eq!(cursor.position(), 0);
+
+        let mut cursor = Cursor::from(r#""abc\""#);
+        assert_eq!(
+            cursor.consume_quoted().unwrap_err(),
+            EscapeError::UnterminatedLiteral
+        );
+
+        let mut cursor = Cursor::from("abc");
+        assert_eq!(
+            cursor.consume_quoted().unwrap_err(),
+            EscapeError::MissingOpeningQuote
+        );
+    }
+
+    #[test]
+    fn test_classify_escapes() {
+        assert_eq!(
+            classify_escapes(r"a\n\x41\u{42}", Mode::Double),
+            [
+                (0..1, EscapeKind::Literal),
+                (1..3, EscapeKind::Simple),
+                (3..7, EscapeKind::Hex),
+                (7..13, EscapeKind::Unicode),
+            ]
+        );
+        assert_eq!(
+            classify_escapes(r"ab\tcd\\", Mode::Double),
+            [
+                (0..2, EscapeKind::Literal),
+                (2..4, EscapeKind::Simple),
+                (4..6, EscapeKind::Literal),
+                (6..8, EscapeKind::Simple),
+            ]
+        );
+        assert_eq!(
+            classify_escapes(r"\uD83D\uDE00!", Mode::Json),
+            [(0..12, EscapeKind::Unicode), (12..13, EscapeKind::Literal)]
+        );
+        assert!(classify_escapes("", Mode::Double).is_empty());
+    }
+
+    #[test]
+    fn test_escape_error_is_fatal() {
+        assert!(!EscapeError::BareCarriageReturn.is_fatal());
+        assert!(!EscapeError::EscapeOnlyChar.is_fatal());
+        assert!(!EscapeError::InvalidEscape.is_fatal());
+
+        assert!(EscapeError::LoneSlash.is_fatal());
+        assert!(EscapeError::InvalidCharInHexEscape.is_fatal());
+        assert!(EscapeError::OutOfRangeUnicodeEscape.is_fatal());
+        assert!(EscapeError::LoneSurrogateUnicodeEscape.is_fatal());
+        assert!(EscapeError::UnterminatedLiteral.is_fatal());
+    }
+
+    #[test]
+    fn test_unescape_str_resume() {
+        let mut cursor = Cursor::from(r#""a\tb" rest"#);
+        let mut literal = cursor.consume_quoted().unwrap();
+        assert_eq!(literal.position(), 1);
+
+        let mut unescaped = String::new();
+        unescape_str_resume(&mut literal, Mode::Double, &mut |range, c| {
+            assert!(range.end <= 4);
+            unescaped.push(c.unwrap());
+        });
+        assert_eq!(unescaped, "a\tb");
+        assert!(literal.is_empty());
+        assert_eq!(literal.position(), 5);
+        assert_eq!(&literal.input()[literal.position()..], r#"" rest"#);
+    }
+
+    #[test]
+    fn test_unescape_write() {
+        struct Limited(String);
+
+        impl fmt::Write for Limited {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                if self.0.len() + s.len() > 2 {
+                    return Err(fmt::Error);
+                }
+                self.0.push_str(s);
+                Ok(())
+            }
+        }
+
+        let mut out = String::from(">");
+        assert_eq!(unescape_write(r"a\u{42}\n", Mode::Double, &mut out), Ok(()));
+        assert_eq!(out, ">aB\n");
+
+        assert_eq!(
+            unescape_write(r"a\q", Mode::Double, &mut out),
+            Err(UnescapeWriteError::Escape(1..3, EscapeError::InvalidEscape))
+        );
+
+        let mut out = Limited(String::new());
+        assert_eq!(
+            unescape_write("abc", Mode::Double, &mut out),
+            Err(UnescapeWriteError::Fmt(fmt::Error))
+        );
+        assert_eq!(out.0, "ab");
+    }
+
+    #[test]
+    fn test_strict_quotes() {
+        fn check(mode: Mode, literal: &str, expected: &[Result<char, EscapeError>]) {
+            let mut unescaped = vec![];
+            unescape_str(Cursor::from(literal), mode, &mut |_, c| unescaped.push(c));
+            assert_eq!(unescaped, expected);
+        }
+
+        check(Mode::Double, "it's", &[Ok('i'), Ok('t'), Ok('\''), Ok('s')]);
+        check(
+            Mode::DoubleStrict,
+            "it's",
+            &[
+                Ok('i'),
+                Ok('t'),
+                Err(EscapeError::UnescapedOppositeQuote),
+                Ok('s'),
+            ],
+        );
+        check(
+            Mode::DoubleStrict,
+            r"it\'s",
+            &[Ok('i'), Ok('t'), Ok('\''), Ok('s')],
+        );
+        check(
+            Mode::DoubleStrict,
+            "\"",
+            &[Err(EscapeError::EscapeOnlyChar)],
+        );
+
+        check(Mode::Single, "\"", &[Ok('"')]);
+        check(
+            Mode::SingleStrict,
+            "\"",
+            &[Err(EscapeError::UnescapedOppositeQuote)],
+        );
+        check(Mode::SingleStrict, "'", &[Err(EscapeError::EscapeOnlyChar)]);
+    }
+
+    #[test]
+    fn test_escape_error_code() {
+        let all = [
+            EscapeError::ZeroChars,
+            EscapeError::MoreThanOneChar,
+            EscapeError::MissingOpeningQuote,
+            EscapeError::UnterminatedLiteral,
+            EscapeError::LoneSlash,
+            EscapeError::InvalidEscape,
+            EscapeError::BareCarriageReturn,
+            EscapeError::EscapeOnlyChar,
+            EscapeError::UnescapedOppositeQuote,
+            EscapeError::TooShortHexEscape,
+            EscapeError::InvalidCharInHexEscape,
+            EscapeError::NoBraceInUnicodeEscape,
+            EscapeError::InvalidCharInUnicodeEscape,
+            EscapeError::EmptyUnicodeEscape,
+            EscapeError::UnclosedUnicodeEscape,
+            EscapeError::LeadingUnderscoreUnicodeEscape,
+            EscapeError::OverlongUnicodeEscape,
+            EscapeError::LoneSurrogateUnicodeEscape,
+            EscapeError::OutOfRangeUnicodeEscape,
+            EscapeError::LineContinuationInChar,
+        ];
+        for (i, error) in all.iter().enumerate() {
+            assert_eq!(usize::from(error.code()), i + 1);
+            assert_eq!(EscapeError::from_code(error.code()), Some(*error));
+        }
+        assert_eq!(EscapeError::from_code(0), None);
+        assert_eq!(EscapeError::from_code(all.len() as u16 + 1), None);
+    }
+
+    #[test]
+    fn test_line_continuation_in_char() {
+        assert_eq!(
+            unescape_char("\\\n", Mode::Single),
+            Err(EscapeError::LineContinuationInChar)
+        );
+
+        let mut errors = vec![];
+        unescape_str(Cursor::from("\\\n"), Mode::SingleStrict, &mut |range, c| {
+            errors.push((range, c))
+        });
+        assert_eq!(errors, [(0..2, Err(EscapeError::LineContinuationInChar))]);
+
+        assert_eq!(
+            unescape_char("\\\r\n", Mode::Single),
+            Err(EscapeError::LineContinuationInChar)
+        );
+
+        let mut errors = vec![];
+        unescape_str(
+            Cursor::from("\\\r\n"),
+            Mode::SingleStrict,
+            &mut |range, c| errors.push((range, c)),
+        );
+        assert_eq!(errors, [(0..3, Err(EscapeError::LineContinuationInChar))]);
+
+        assert_eq!(
+            unescape_to_string("a\\\nb", Mode::Double),
+            Err((1..3, EscapeError::InvalidEscape))
+        );
+    }
+
+    #[test]
+    fn test_escape_error_category() {
+        use EscapeErrorCategory as C;
+        assert_eq!(EscapeError::UnterminatedLiteral.category(), C::Literal);
+        assert_eq!(EscapeError::LoneSlash.category(), C::Slash);
+        assert_eq!(EscapeError::InvalidEscape.category(), C::SimpleEscape);
+        assert_eq!(EscapeError::InvalidCharInHexEscape.category(), C::HexEscape);
+        assert_eq!(
+            EscapeError::OverlongUnicodeEscape.category(),
+            C::UnicodeEscape
+        );
+        assert_eq!(EscapeError::BareCarriageReturn.category(), C::BareChar);
+    }
+
+    #[test]
+    fn test_unescape_cow() {
+        let input = "it's plain";
+        let unescaped = unescape_cow(input, Mode::Double).unwrap();
+        assert!(matches!(unescaped, Cow::Borrowed(s) if std::ptr::eq(s, input)));
+
+        assert_eq!(
+            unescape_cow(r"a\tb", Mode::Double),
+            Ok(Cow::Owned::<str>("a\tb".to_string()))
+        );
+        assert_eq!(
+            unescape_cow("it's", Mode::DoubleStrict),
+            Err((2..3, EscapeError::UnescapedOppositeQuote))
+        );
+        assert_eq!(
+            unescape_cow("a\rb", Mode::Single),
+            Err((1..2, EscapeError::BareCarriageReturn))
+        );
+        assert_eq!(
+            unescape_cow("\"", Mode::Json),
+            Err((0..1, EscapeError::EscapeOnlyChar))
+        );
+        assert!(matches!(
+            unescape_cow("\"", Mode::Single),
+            Ok(Cow::Borrowed("\""))
+        ));
+    }
+
+    #[test]
+    fn test_scan_hex_escape() {
+        let mut cursor = Cursor::from("1b[0m");
+        assert_eq!(scan_hex_escape(&mut cursor, 2), Ok(0x1b));
+        assert_eq!(cursor.as_str(), "[0m");
+        assert_eq!(scan_hex_escape(&mut Cursor::from("fF"), 2), Ok(0xff));
+        assert_eq!(
+            scan_hex_escape(&mut Cursor::from("1"), 2),
+            Err(EscapeError::TooShortHexEscape)
+        );
+        assert_eq!(
+            scan_hex_escape(&mut Cursor::from("1g"), 2),
+            Err(EscapeError::InvalidCharInHexEscape)
+        );
+        assert_eq!(
+            scan_hex_escape(&mut Cursor::from("ffffffff"), 8),
+            Ok(u32::MAX)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "more hex digits than fit a u32")]
+    fn test_scan_hex_escape_too_many_digits() {
+        let _ = scan_hex_escape(&mut Cursor::from("fffffffff"), 9);
+    }
+
+    #[test]
+    fn test_scan_unicode_brace_escape() {
+        let mut cursor = Cursor::from("{1F_980}rest");
+        assert_eq!(scan_unicode_brace_escape(&mut cursor), Ok('🦀'));
+        assert_eq!(cursor.as_str(), "rest");
+
+        let check = |input: &str, error| {
+            assert_eq!(
+                scan_unicode_brace_escape(&mut Cursor::from(input)),
+                Err(error)
+            );
+        };
+        check("41}", EscapeError::NoBraceInUnicodeEscape);
+        check("{}", EscapeError::EmptyUnicodeEscape);
+        check("{_41}", EscapeError::LeadingUnderscoreUnicodeEscape);
+        check("{41", EscapeError::UnclosedUnicodeEscape);
+        check("{1234567}", EscapeError::OverlongUnicodeEscape);
+        check("{D800}", EscapeError::LoneSurrogateUnicodeEscape);
+        check("{110000}", EscapeError::OutOfRangeUnicodeEscape);
+    }
+
+    #[test]
+    fn test_partition_escapes() {
+        let (unescaped, errors, warnings) = partition_escapes(r"a\zb\x4gc\u{D800}", Mode::Double);
+        assert_eq!(unescaped, "a\\zb\u{FFFD}c\u{FFFD}");
+        assert_eq!(
+            errors,
+            [
+                (4..8, EscapeError::InvalidCharInHexEscape),
+                (9..17, EscapeError::LoneSurrogateUnicodeEscape),
+            ]
+        );
+        assert_eq!(warnings, [(1..3, EscapeError::InvalidEscape)]);
+
+        let (unescaped, errors, warnings) = partition_escapes("x\ry", Mode::Single);
+        assert_eq!(unescaped, "x\ry");
+        assert!(errors.is_empty());
+        assert_eq!(warnings, [(1..2, EscapeError::BareCarriageReturn)]);
+    }
 }